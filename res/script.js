@@ -1,16 +1,115 @@
 const host = window.location.href.substr(7);
 let login_button = document.getElementById("login");
 let username = document.getElementById("nameinput");
+let password = document.getElementById("passwordinput");
 let canvas;
 let ctx;
 let keys_down = [];
 let wheel = 0;
+let socket = null;
+// the bindings the server sent last, key or button name to action
+let controls = {};
+// action waiting for the next key or button, see rebind
+let rebinding = null;
+
+// standard gamepad layout, the server binds these names like key codes
+const gamepad_buttons = ["GamepadA", "GamepadB", "GamepadX", "GamepadY", "GamepadLB", "GamepadRB", "GamepadLT", "GamepadRT", "GamepadBack", "GamepadStart", "GamepadLS", "GamepadRS", "GamepadUp", "GamepadDown", "GamepadLeft", "GamepadRight"];
+let gamepad_down = [];
+
+function gamepad_keys() {
+	let pressed = [];
+	if (!navigator.getGamepads) {
+		return pressed;
+	}
+	for (let pad of navigator.getGamepads()) {
+		if (!pad) continue;
+		pad.buttons.forEach(function(button, i) {
+			if (button.pressed && i < gamepad_buttons.length && !pressed.includes(gamepad_buttons[i])) {
+				pressed.push(gamepad_buttons[i]);
+			}
+		});
+	}
+	if (rebinding != null) {
+		let fresh = pressed.find(function(b) { return !gamepad_down.includes(b); });
+		if (fresh) {
+			send_bind(fresh, rebinding);
+			rebinding = null;
+		}
+	}
+	gamepad_down = pressed;
+	return pressed;
+}
+
+// left stick of the first gamepad that is moved, the server applies the deadzone
+function gamepad_stick() {
+	if (!navigator.getGamepads) {
+		return null;
+	}
+	for (let pad of navigator.getGamepads()) {
+		if (!pad || pad.axes.length < 2) continue;
+		if (pad.axes[0] != 0 || pad.axes[1] != 0) {
+			return [pad.axes[0], pad.axes[1]];
+		}
+	}
+	return null;
+}
+
+// touch joystick, dragging this far from where the finger landed is full speed
+const touch_radius = 100;
+let touch_start = null;
+let touch_vector = null;
+
+document.ontouchstart = function(e) {
+	let touch = e.changedTouches[0];
+	touch_start = [touch.clientX, touch.clientY];
+	touch_vector = null;
+}
+
+document.ontouchmove = function(e) {
+	if (touch_start == null) return;
+	e.preventDefault();
+	let touch = e.changedTouches[0];
+	let x = (touch.clientX - touch_start[0]) / touch_radius;
+	let y = (touch.clientY - touch_start[1]) / touch_radius;
+	let length = Math.sqrt(x * x + y * y);
+	if (length > 1) {
+		x /= length;
+		y /= length;
+	}
+	touch_vector = [x, y];
+}
+
+document.ontouchend = function(_e) {
+	touch_start = null;
+	touch_vector = null;
+}
+
+function send_bind(key, action) {
+	if (socket) {
+		socket.send(JSON.stringify({mode: "bind", username: username.value, key: key, action: action}));
+	}
+}
+// from the browser console: rebind("MoveUp") or rebind({"UseItem": 0}) binds the next pressed key or button
+function rebind(action) {
+	rebinding = action;
+}
+function unbind(key) {
+	send_bind(key, null);
+}
+function reset_controls() {
+	send_bind(null, null);
+}
 
 document.onwheel = function(e) {
   wheel = e.deltaY;
 }
 
 document.onkeydown = function(e) {
+	if (rebinding != null) {
+		send_bind(e.code, rebinding);
+		rebinding = null;
+		return;
+	}
  	if (keys_down.includes(e.code)) {
 		return;
 	}
@@ -229,9 +328,12 @@ function render(data) {
 
 login_button.onclick = function(_e) {
   let ws = new WebSocket("ws://" + host);
+  socket = ws;
   ws.onopen = function() {
     // here comes what happens after login
-    let loginmsg = JSON.stringify({mode: "login", username: username.value, x: mouse_x, y: mouse_y, keys_down: keys_down, wheel: wheel});
+    // a token from an earlier login replaces the password
+    let token = localStorage.getItem("token:" + username.value);
+    let loginmsg = JSON.stringify({mode: "login", username: username.value, x: mouse_x, y: mouse_y, keys_down: keys_down, wheel: wheel, password: password.value, token: password.value ? null : token});
     ws.send(loginmsg);
     document.body.innerHTML = "";
     canvas = document.createElement("canvas");
@@ -242,16 +344,34 @@ login_button.onclick = function(_e) {
     document.body.style.margin = "0";
     f = canvas.width / 1920;
 
+    let name = username.value;
     ws.onmessage = function(e) {
+      if (e.data.startsWith("{\"token\"")) {
+        localStorage.setItem("token:" + name, JSON.parse(e.data).token);
+        return;
+      }
+      if (e.data.startsWith("{\"controls\"")) {
+        controls = JSON.parse(e.data).controls;
+        return;
+      }
       render(e.data);
     };
-    ws.onclose = function() {
+    ws.onclose = function(e) {
       clearInterval(renderLoop);
+      if (e.reason) {
+        if (e.reason == "invalid token") {
+          localStorage.removeItem("token:" + name);
+        }
+        rect(0, 0, canvas.width, canvas.height, "rgba(0,0,0,0.7)");
+        ctx.fillStyle = "white";
+        ctx.font = 40 * f + "px Arial";
+        ctx.fillText(e.reason, canvas.width / 2 - 200 * f, canvas.height / 2);
+      }
     }
 
     // starting canvas action
     renderLoop = setInterval(function() {
-      let gamemsg = JSON.stringify({mode: "game", username: username.value, x: mouse_x, y: mouse_y, keys_down: keys_down, wheel: wheel});
+      let gamemsg = JSON.stringify({mode: "game", username: username.value, x: mouse_x, y: mouse_y, keys_down: keys_down.concat(gamepad_keys()), wheel: wheel, movement: touch_vector || gamepad_stick()});
       // let msg = `let mode = game; let username: String = ${username.value}; let x: i32 = ${mouse_x}; let y: i32 = ${mouse_y}; let keys_down = ${keys_down.join(",")}; let wheel = ${wheel};`;
       wheel = 0;
       try {
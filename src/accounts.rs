@@ -0,0 +1,284 @@
+use std::{collections::HashMap, fs, io, path::{Path, PathBuf}, sync::mpsc::{channel, Sender}, thread::{self, JoinHandle}};
+
+use argon2::{password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString}, Argon2};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+
+use crate::persistence::SavedPlayer;
+
+// what a client can log in with besides the name
+#[derive(Debug, Clone, PartialEq)]
+pub enum Credential {
+    Password(String),
+    // handed out after a password login so the client doesnt have to keep the password
+    Token(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Account {
+    password_hash: String,
+    pub token: String,
+    // inventory, bindings, position, discovered areas and stats of the last logout
+    pub player: Option<SavedPlayer>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Login {
+    // no credential for a name without an account, plays like before accounts existed
+    Guest,
+    Account { token: String, created: bool },
+}
+
+// what happens to the inventory of a player that logs out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogoutDrop {
+    Everything,
+    // accounts keep their items, guests drop them
+    GuestsOnly,
+    Nothing,
+}
+
+#[derive(Default)]
+pub struct Accounts {
+    path: Option<PathBuf>,
+    pub accounts: HashMap<String, Account>,
+    // changed since the file was last written
+    dirty: bool,
+    // writes the file in the background, started by the first write_later
+    writer: Option<(Sender<String>, JoinHandle<()>)>,
+}
+
+// a credential after the password work of the connection thread, the game only compares and stores it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Proof {
+    // the password matched this hash of the account
+    Password(String),
+    // the hash for a new account
+    NewAccount(String),
+    Token(String),
+}
+
+fn random_hex(bytes: usize) -> String {
+    (0..bytes).map(|_| format!("{:02x}", rand::thread_rng().gen::<u8>())).collect()
+}
+
+// argon2 with its default cost, the string keeps the salt and the parameters with the hash
+fn hash(password: &str) -> String {
+    let salt: [u8; 16] = rand::thread_rng().gen();
+    let salt = SaltString::encode_b64(&salt).expect("16 bytes are a valid salt");
+    Argon2::default().hash_password(password.as_bytes(), &salt).expect("argon2 with default parameters").to_string()
+}
+
+// takes the same time no matter how much of a guess is right
+pub fn constant_eq(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}
+
+fn verify(password_hash: &str, password: &str) -> bool {
+    match PasswordHash::new(password_hash) {
+        Ok(parsed) => Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok(),
+        Err(_) => false,
+    }
+}
+
+// the slow argon2 part of a login, connection threads run it with the hash the game has for the name
+pub fn prove(password_hash: Option<&str>, credential: Option<Credential>) -> Result<Option<Proof>, String> {
+    match (password_hash, credential) {
+        (_, None) => Ok(None),
+        (_, Some(Credential::Token(token))) => Ok(Some(Proof::Token(token))),
+        (_, Some(Credential::Password(password))) if password.is_empty() => Ok(None),
+        (None, Some(Credential::Password(password))) => Ok(Some(Proof::NewAccount(hash(&password)))),
+        (Some(password_hash), Some(Credential::Password(password))) => {
+            if verify(password_hash, &password) {
+                Ok(Some(Proof::Password(password_hash.to_owned())))
+            }
+            else {
+                Err("wrong password".to_owned())
+            }
+        },
+    }
+}
+
+fn write(path: &Path, json: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, path)
+}
+
+impl Accounts {
+    // a missing file is an empty store, the file is created on the first save
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Accounts> {
+        let mut accounts = Accounts {
+            path: Some(path.as_ref().to_path_buf()),
+            ..Default::default()
+        };
+        match fs::read_to_string(&path) {
+            Ok(data) => {
+                accounts.accounts = serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+            Err(e) => return Err(e),
+        }
+        Ok(accounts)
+    }
+    // hands the changes to the writer thread so the tick doesnt wait for the disk
+    pub fn write_later(&mut self) {
+        let path = match &self.path {
+            Some(p) if self.dirty => p.clone(),
+            _ => return,
+        };
+        self.dirty = false;
+        let json = serde_json::to_string_pretty(&self.accounts).unwrap();
+        let (sender, _) = self.writer.get_or_insert_with(|| {
+            let (sender, receiver) = channel::<String>();
+            let handle = thread::spawn(move || {
+                while let Ok(mut json) = receiver.recv() {
+                    // only the newest state matters when writes pile up
+                    while let Ok(newer) = receiver.try_recv() {
+                        json = newer;
+                    }
+                    if let Err(e) = write(&path, &json) {
+                        println!("saving accounts failed: {}", e);
+                    }
+                }
+            });
+            (sender, handle)
+        });
+        let _ = sender.send(json);
+    }
+    // waits for the writer thread and writes right away, for the shutdown
+    pub fn save(&mut self) -> io::Result<()> {
+        if let Some((sender, handle)) = self.writer.take() {
+            drop(sender);
+            let _ = handle.join();
+        }
+        let path = match &self.path {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+        self.dirty = false;
+        write(path, &serde_json::to_string_pretty(&self.accounts).unwrap())
+    }
+    // a proof of a new account creates it
+    pub fn authenticate(&mut self, name: &str, proof: &Option<Proof>) -> Result<Login, String> {
+        let account = self.accounts.get(name);
+        match (account, proof) {
+            (None, None) => Ok(Login::Guest),
+            (None, Some(Proof::NewAccount(password_hash))) => {
+                let account = Account {
+                    password_hash: password_hash.clone(),
+                    token: random_hex(16),
+                    player: None,
+                };
+                let token = account.token.clone();
+                self.accounts.insert(name.to_owned(), account);
+                self.dirty = true;
+                Ok(Login::Account { token, created: true })
+            },
+            (None, Some(_)) => Err("unknown account".to_owned()),
+            (Some(_), None) => Err("this name belongs to an account, a password is needed".to_owned()),
+            // somebody else created the account while the password was hashed
+            (Some(_), Some(Proof::NewAccount(_))) => Err("this name was just taken by an account".to_owned()),
+            // the hash changes only with a new account, so a match is the password the connection checked
+            (Some(account), Some(Proof::Password(password_hash))) => {
+                if constant_eq(password_hash, &account.password_hash) {
+                    Ok(Login::Account { token: account.token.clone(), created: false })
+                }
+                else {
+                    Err("wrong password".to_owned())
+                }
+            },
+            (Some(account), Some(Proof::Token(token))) => {
+                if constant_eq(token, &account.token) {
+                    Ok(Login::Account { token: account.token.clone(), created: false })
+                }
+                else {
+                    Err("invalid token".to_owned())
+                }
+            },
+        }
+    }
+    pub fn password_hash(&self, name: &str) -> Option<String> {
+        self.accounts.get(name).map(|a| a.password_hash.clone())
+    }
+    pub fn store(&mut self, name: &str, player: SavedPlayer) {
+        if let Some(account) = self.accounts.get_mut(name) {
+            account.player = Some(player);
+            self.dirty = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod accounts_tests {
+    use std::sync::mpsc::channel;
+
+    use crate::{game::Game, player::Player};
+
+    use super::*;
+
+    // what a connection and the game do together for a login
+    fn login(accounts: &mut Accounts, name: &str, credential: Credential) -> Result<Login, String> {
+        let proof = prove(accounts.password_hash(name).as_deref(), Some(credential))?;
+        accounts.authenticate(name, &proof)
+    }
+
+    #[test]
+    fn logout_drop_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.logout_drop = LogoutDrop::GuestsOnly;
+        let collectables = game.collectables.len();
+        for (name, account) in [("guest", false), ("member", true)] {
+            let mut player = Player::new(&name.to_owned());
+            player.account = account;
+            player.inventory.items.push(game.create_item("monocle").unwrap());
+            game.players.push(player);
+        }
+        login(&mut game.accounts, "member", Credential::Password("pw".to_owned())).unwrap();
+        game.logout(&"guest".to_owned());
+        game.logout(&"member".to_owned());
+        assert_eq!(game.collectables.len(), collectables + 1);
+        let saved = game.accounts.accounts["member"].player.as_ref().unwrap();
+        assert_eq!(saved.items.len(), 1);
+    }
+
+    #[test]
+    fn authenticate_test() {
+        let mut accounts = Accounts::default();
+        assert_eq!(accounts.authenticate("jo", &None), Ok(Login::Guest));
+        let token = match login(&mut accounts, "jo", Credential::Password("pw".to_owned())) {
+            Ok(Login::Account { token, created: true }) => token,
+            other => panic!("account not created: {:?}", other),
+        };
+        assert!(accounts.authenticate("jo", &None).is_err());
+        assert!(login(&mut accounts, "jo", Credential::Password("wrong".to_owned())).is_err());
+        assert_eq!(login(&mut accounts, "jo", Credential::Password("pw".to_owned())), Ok(Login::Account { token: token.clone(), created: false }));
+        assert_eq!(login(&mut accounts, "jo", Credential::Token(token.clone())), Ok(Login::Account { token, created: false }));
+        assert!(login(&mut accounts, "jo", Credential::Token("other".to_owned())).is_err());
+        assert!(login(&mut accounts, "max", Credential::Token("other".to_owned())).is_err());
+        // two connections creating the same account, the second one is too late
+        let late = prove(None, Some(Credential::Password("other".to_owned()))).unwrap();
+        assert!(accounts.authenticate("jo", &late).is_err());
+        // a proof only counts for the hash it was made with
+        assert!(accounts.authenticate("jo", &Some(Proof::Password("$argon2id$other".to_owned()))).is_err());
+        assert!(accounts.accounts["jo"].password_hash.starts_with("$argon2"));
+    }
+
+    #[test]
+    fn write_later_test() {
+        let path = std::env::temp_dir().join(format!("dodgescape_accounts_test_{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut accounts = Accounts::load(&path).unwrap();
+        login(&mut accounts, "jo", Credential::Password("pw".to_owned())).unwrap();
+        accounts.write_later();
+        accounts.save().unwrap();
+        let loaded = Accounts::load(&path).unwrap();
+        assert_eq!(loaded.accounts["jo"].token, accounts.accounts["jo"].token);
+        let _ = fs::remove_file(&path);
+    }
+}
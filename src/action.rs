@@ -1,5 +1,5 @@
 
-use crate::{enemy::{Enemy, EnemyEffect}, game::{get_enemy, get_player, DrawPack, Game}, gametraits::{Moveable, Radius}, player::{Player, PlayerEffect}, vector, Float};
+use crate::{enemy::{Enemy, EnemyEffect}, game::{get_enemy, get_player, DrawPack, Game}, gametraits::{Moveable, Radius}, worldobject::WorldObject, player::{Player, PlayerEffect}, vector, Float};
 
 pub enum Action {
     AddPlayerPosition((Float,Float)),
@@ -9,7 +9,6 @@ pub enum Action {
     AddPlayerVelocity((Float,Float)),
     SetPlayerInvincible(bool),
     SetItemActive{i: usize, v: bool},
-    DecreaseItemEffect {item: usize, effect: usize},
     DecrementEnemyEase{group: usize, effect: usize},
     Despawn(usize),
     MulEnemyRadiusMultiplier {f: Float, group: usize},
@@ -38,6 +37,7 @@ pub enum Action {
     SpawnProjectile { group: usize, velocity: (Float, Float), radius: Float, color: String, lifetime: usize, effects: Vec<EnemyEffect>, under_dps: Vec<DrawPack> },
     UpdateEnemyVelocity(usize, (Float,Float)),
     RevivePlayers {radius: Radius},
+    SpawnWorldObject(WorldObject),
 }
 
 impl Action {
@@ -75,6 +75,7 @@ impl Action {
                 // cumble
                 let mut crumble = Enemy::new(x, y, vector::normalize(v, 0.5), r, "rgb(0,0,0)");
                 crumble.effects.push(EnemyEffect::Lifetime(2000));
+                crumble.solid = false;
                 game.enemies.get_mut(*g).unwrap().1.push(crumble);
             },
             Action::Despawn(g) => {
@@ -137,6 +138,7 @@ impl Action {
                 }
                 projectile.effects = effects.clone();
                 projectile.effects.push(EnemyEffect::Lifetime(*lifetime));
+                projectile.solid = false;
                 game.enemies.get_mut(*group).unwrap().1.push(projectile);
             },
             Action::SpawnEnemy { group, velocity, color, radius, effects } => {
@@ -234,21 +236,6 @@ impl Action {
                 let player = game.players.get_mut(entity).unwrap();
                 player.inventory.items.remove(*item);
             },
-            Action::DecreaseItemEffect { item: i, effect: e } => {
-                let player = game.players.get_mut(entity).unwrap();
-                let item = player.inventory.items.get_mut(*i).unwrap();
-                let effect = item.effects.get_mut(*e).unwrap();
-                match effect {
-                    crate::item::ItemEffect::Consumable { uses } => {
-                        *uses -= 1;
-                        item.active = false;
-                        if *uses == 0 {
-                            player.inventory.items.remove(*i);
-                        }
-                    },
-                    _ => {}
-                };
-            },
             Action::RevivePlayers { radius } => {
                 let center = game.players.get_mut(entity).unwrap();
                 let r = center.radius;
@@ -285,6 +272,9 @@ impl Action {
                 let enemy = get_enemy(game, *group, entity);
                 enemy.old_position = (enemy.x, enemy.y);
             },
+            Action::SpawnWorldObject(object) => {
+                game.world_objects.push(object.clone());
+            },
         }
     }
 }
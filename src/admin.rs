@@ -0,0 +1,306 @@
+use rand::Rng;
+use serde_json::{json, Value};
+
+use crate::{accounts::constant_eq, game::Game, gametraits::{Moveable, Position}, http::Http_request, player::Player, Float};
+
+// step and speed run on the game thread, more would freeze the server
+pub const MAX_STEP_TICKS: usize = 1000;
+pub const MAX_SPEED: Float = 10.0;
+
+// commands of the admin api, executed on the game thread between two ticks
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdminCommand {
+    Players,
+    Kick { name: String },
+    Teleport { name: String, x: Float, y: Float },
+    Give { name: String, item: String },
+    Remove { name: String, item: String },
+    // group is the spawner name or the index of the group
+    Respawn { group: String },
+    // single enemy copied from a random member of the group
+    Spawn { archetype: String, x: Float, y: Float },
+    Kill { name: String },
+    Revive { name: String },
+    Speed { multiplier: Float },
+    Pause,
+    Resume,
+    // runs ticks right away, also while paused
+    Step { ticks: usize },
+    Reload,
+    // saves the players and closes every connection
+    Shutdown,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AdminError {
+    NotFound,
+    BadRequest(String),
+}
+
+impl AdminCommand {
+    // route is the path after /admin/, body a json object with the arguments
+    pub fn parse(method: &str, route: &str, body: &str) -> Result<AdminCommand, AdminError> {
+        let args: Value = if body.trim().is_empty() {
+            json!({})
+        }
+        else {
+            serde_json::from_str(body).map_err(|e| {AdminError::BadRequest(format!("invalid json: {}", e))})?
+        };
+        let command = match (method, route) {
+            ("GET", "players") => AdminCommand::Players,
+            ("POST", "kick") => AdminCommand::Kick { name: string(&args, "name")? },
+            ("POST", "teleport") => AdminCommand::Teleport { name: string(&args, "name")?, x: number(&args, "x")?, y: number(&args, "y")? },
+            ("POST", "give") => AdminCommand::Give { name: string(&args, "name")?, item: string(&args, "item")? },
+            ("POST", "remove") => AdminCommand::Remove { name: string(&args, "name")?, item: string(&args, "item")? },
+            ("POST", "respawn") => {
+                let group = match args.get("group") {
+                    Some(Value::Number(n)) => n.to_string(),
+                    _ => string(&args, "group")?,
+                };
+                AdminCommand::Respawn { group }
+            },
+            ("POST", "spawn") => AdminCommand::Spawn { archetype: string(&args, "archetype")?, x: number(&args, "x")?, y: number(&args, "y")? },
+            ("POST", "kill") => AdminCommand::Kill { name: string(&args, "name")? },
+            ("POST", "revive") => AdminCommand::Revive { name: string(&args, "name")? },
+            ("POST", "speed") => AdminCommand::Speed { multiplier: number(&args, "multiplier")? },
+            ("POST", "pause") => AdminCommand::Pause,
+            ("POST", "resume") => AdminCommand::Resume,
+            ("POST", "step") => AdminCommand::Step { ticks: number(&args, "ticks")? as usize },
+            ("POST", "reload") => AdminCommand::Reload,
+            ("POST", "shutdown") => AdminCommand::Shutdown,
+            _ => return Err(AdminError::NotFound),
+        };
+        Ok(command)
+    }
+}
+
+fn string(args: &Value, key: &str) -> Result<String, AdminError> {
+    match args.get(key).and_then(|v| v.as_str()) {
+        Some(s) => Ok(s.to_owned()),
+        None => Err(AdminError::BadRequest(format!("missing string {}", key))),
+    }
+}
+fn number(args: &Value, key: &str) -> Result<Float, AdminError> {
+    match args.get(key).and_then(|v| v.as_f64()) {
+        Some(n) => Ok(n as Float),
+        None => Err(AdminError::BadRequest(format!("missing number {}", key))),
+    }
+}
+
+// admin routes are disabled if no token is configured
+pub fn authorized(request: &Http_request, token: &Option<String>) -> bool {
+    let token = match token {
+        Some(t) => t,
+        None => return false,
+    };
+    match request.get_header("Authorization".to_owned()) {
+        Some(header) => match header.trim().strip_prefix("Bearer ") {
+            Some(given) => constant_eq(given, token),
+            None => false,
+        },
+        None => false,
+    }
+}
+
+pub fn player_info(player: &Player) -> Value {
+    let items: Vec<Value> = player.inventory.items.iter().map(|item| {
+        json!({"id": item.id, "kind": item.kind, "name": item.name, "count": item.count, "active": item.active, "cooldown_left": item.cooldown_left, "charges_left": item.charges.map(|_| item.charges_left)})
+    }).collect();
+    let effects: Vec<String> = player.effects.iter().map(|e| format!("{:?}", e)).collect();
+    json!({
+        "name": player.name,
+        "x": player.get_x(),
+        "y": player.get_y(),
+        "alive": player.alive,
+        "downed": player.downed,
+        "invincible": player.invincible,
+        "account": player.account,
+        "discovered": player.discovered,
+        "stats": player.stats,
+        "waypoints": player.waypoints,
+        "checkpoint": player.checkpoint,
+        "inventory": items,
+        "effects": effects,
+    })
+}
+
+pub fn execute(game: &mut Game, command: &AdminCommand) -> Result<Value, String> {
+    match command {
+        AdminCommand::Players => {
+            Ok(Value::Array(game.players.iter().map(player_info).collect()))
+        },
+        AdminCommand::Kick { name } => {
+            if game.get(name).is_none() {
+                return Err(format!("no player {}", name));
+            }
+            game.logout(name);
+            Ok(json!({"kicked": name}))
+        },
+        AdminCommand::Teleport { name, x, y } => {
+            let player = game.get_mut(name).ok_or(format!("no player {}", name))?;
+            player.x = *x;
+            player.y = *y;
+            player.old_position = (*x, *y);
+            Ok(player_info(player))
+        },
+        AdminCommand::Give { name, item } => {
+            if game.get(name).is_none() {
+                return Err(format!("no player {}", name));
+            }
+            let item = game.create_item(item).ok_or(format!("no item {}", item))?;
+            let player = game.get_mut(name).unwrap();
+            player.inventory.add(item).map_err(|_| format!("the inventory of {} is full", name))?;
+            Ok(player_info(player))
+        },
+        AdminCommand::Remove { name, item } => {
+            let player = game.get_mut(name).ok_or(format!("no player {}", name))?;
+            let index = player.inventory.items.iter().position(|i| {i.kind == *item || i.name == *item}).ok_or(format!("{} has no {}", name, item))?;
+            player.inventory.remove(index);
+            Ok(player_info(player))
+        },
+        AdminCommand::Respawn { group } => {
+            let g = match group.parse::<usize>() {
+                Ok(g) => Some(g),
+                Err(_) => game.enemy_group(group),
+            };
+            match g {
+                Some(g) if game.respawn_enemy_group(g) => {
+                    Ok(json!({"group": game.enemy_groups[g], "enemies": game.enemies[g].1.len()}))
+                },
+                _ => Err(format!("no enemy group {}", group)),
+            }
+        },
+        AdminCommand::Spawn { archetype, x, y } => {
+            let g = game.enemy_group(archetype).ok_or(format!("no enemy group {}", archetype))?;
+            let group = &mut game.enemies[g].1;
+            if group.is_empty() {
+                return Err(format!("enemy group {} is empty", archetype));
+            }
+            let mut enemy = group[rand::thread_rng().gen_range(0..group.len())].clone();
+            enemy.set_pos(*x, *y);
+            enemy.old_position = (*x, *y);
+            group.push(enemy);
+            Ok(json!({"group": archetype, "enemies": group.len()}))
+        },
+        AdminCommand::Kill { name } => {
+            let player = game.get_mut(name).ok_or(format!("no player {}", name))?;
+            player.alive = false;
+            Ok(player_info(player))
+        },
+        AdminCommand::Revive { name } => {
+            let player = game.get_mut(name).ok_or(format!("no player {}", name))?;
+            player.alive = true;
+            Ok(player_info(player))
+        },
+        AdminCommand::Speed { multiplier } => {
+            if !(*multiplier >= 0.0 && *multiplier <= MAX_SPEED) {
+                return Err(format!("speed has to be between 0 and {}", MAX_SPEED));
+            }
+            game.speed = *multiplier;
+            Ok(json!({"speed": game.speed}))
+        },
+        AdminCommand::Step { ticks } => {
+            if *ticks > MAX_STEP_TICKS {
+                return Err(format!("at most {} ticks at once", MAX_STEP_TICKS));
+            }
+            for _ in 0..*ticks {
+                game.update();
+            }
+            Ok(json!({"tick": game.tick}))
+        },
+        AdminCommand::Reload => {
+            game.reload();
+            Ok(json!({"groups": game.enemy_groups.len(), "collectables": game.collectables.len()}))
+        },
+        AdminCommand::Shutdown => {
+            // the same way as a signal, the game loop stops at the start of the next frame
+            crate::shutdown::request();
+            Ok(json!({"shutdown": true}))
+        },
+        AdminCommand::Pause => {
+            game.paused = true;
+            Ok(json!({"paused": true}))
+        },
+        AdminCommand::Resume => {
+            game.paused = false;
+            Ok(json!({"paused": false}))
+        },
+    }
+}
+
+#[cfg(test)]
+mod admin_tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let command = AdminCommand::parse("POST", "teleport", "{\"name\":\"jo\",\"x\":10,\"y\":-2.5}");
+        assert_eq!(command, Ok(AdminCommand::Teleport { name: "jo".to_owned(), x: 10.0, y: -2.5 }));
+        let command = AdminCommand::parse("POST", "respawn", "{\"group\":3}");
+        assert_eq!(command, Ok(AdminCommand::Respawn { group: "3".to_owned() }));
+        assert_eq!(AdminCommand::parse("GET", "players", ""), Ok(AdminCommand::Players));
+        assert_eq!(AdminCommand::parse("GET", "kick", ""), Err(AdminError::NotFound));
+        assert!(matches!(AdminCommand::parse("POST", "give", "{\"name\":\"jo\"}"), Err(AdminError::BadRequest(_))));
+    }
+
+    #[test]
+    fn authorized_test() {
+        let request = Http_request::parse(&"GET /admin/players HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n".to_owned()).unwrap();
+        assert!(authorized(&request, &Some("secret".to_owned())));
+        assert!(!authorized(&request, &Some("other".to_owned())));
+        assert!(!authorized(&request, &None));
+    }
+
+    #[test]
+    fn give_remove_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        let name = "jo".to_owned();
+        game.players.push(Player::new(&name));
+        execute(&mut game, &AdminCommand::Give { name: name.clone(), item: "monocle".to_owned() }).unwrap();
+        execute(&mut game, &AdminCommand::Give { name: name.clone(), item: "heart".to_owned() }).unwrap();
+        assert!(execute(&mut game, &AdminCommand::Give { name: name.clone(), item: "nothing".to_owned() }).is_err());
+        let heart = game.get(&name).unwrap().inventory.items[1].id;
+        game.get_mut(&name).unwrap().inventory.bindings.insert("KeyH".to_owned(), heart);
+        execute(&mut game, &AdminCommand::Remove { name: name.clone(), item: "monocle".to_owned() }).unwrap();
+        let inventory = &game.get(&name).unwrap().inventory;
+        assert_eq!(inventory.items.len(), 1);
+        assert_eq!(inventory.bindings.get("KeyH"), Some(&heart));
+    }
+
+    #[test]
+    fn step_spawn_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.paused = true;
+        execute(&mut game, &AdminCommand::Step { ticks: 3 }).unwrap();
+        assert_eq!(game.tick, 3);
+        let g = game.enemy_group("snake").unwrap();
+        let enemies = game.enemies[g].1.len();
+        execute(&mut game, &AdminCommand::Spawn { archetype: "snake".to_owned(), x: 5.0, y: 6.0 }).unwrap();
+        let enemy = game.enemies[g].1.last().unwrap();
+        assert_eq!(game.enemies[g].1.len(), enemies + 1);
+        assert_eq!((enemy.get_x(), enemy.get_y()), (5.0, 6.0));
+        assert!(execute(&mut game, &AdminCommand::Speed { multiplier: -1.0 }).is_err());
+        // both would keep the game thread busy for ages
+        assert!(execute(&mut game, &AdminCommand::Speed { multiplier: 1e9 }).is_err());
+        assert!(execute(&mut game, &AdminCommand::Step { ticks: 1e12 as usize }).is_err());
+        assert_eq!(game.tick, 3);
+        assert!(execute(&mut game, &AdminCommand::Speed { multiplier: MAX_SPEED }).is_ok());
+    }
+
+    #[test]
+    fn respawn_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        let g = game.enemy_group("water").unwrap();
+        let groups = game.enemies.len();
+        game.enemies[g].1.clear();
+        execute(&mut game, &AdminCommand::Respawn { group: "water".to_owned() }).unwrap();
+        assert_eq!(game.enemies.len(), groups);
+        assert_eq!(game.enemy_groups[g], "water");
+        assert!(!game.enemies[g].1.is_empty());
+    }
+}
@@ -0,0 +1,216 @@
+use std::{sync::mpsc::channel, time::{Duration, Instant}};
+
+use dodgescape::{game::Game, parallel, player::Player, vector, Float};
+use serde::Serialize;
+
+// headless benchmark of the simulation, no networking involved
+//
+// usage: bench [--players N] [--ticks M] [--threads T] [--compare] [--no-lod] [--json]
+//   --compare  runs the same setup once serial and once with T threads
+//   --json     prints the report as json so runs of different commits can be diffed
+
+struct Options {
+    players: usize,
+    ticks: usize,
+    threads: usize,
+    compare: bool,
+    lod: bool,
+    json: bool,
+}
+
+impl Options {
+    fn parse() -> Result<Options, String> {
+        let mut options = Options {
+            players: 4,
+            ticks: 500,
+            threads: parallel::default_threads(),
+            compare: false,
+            lod: true,
+            json: false,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--players" => options.players = Self::value(&arg, args.next())?,
+                "--ticks" => options.ticks = Self::value(&arg, args.next())?,
+                "--threads" => options.threads = Self::value(&arg, args.next())?,
+                "--compare" => options.compare = true,
+                "--no-lod" => options.lod = false,
+                "--json" => options.json = true,
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+    fn value(arg: &str, value: Option<String>) -> Result<usize, String> {
+        match value.map(|v| v.parse::<usize>()) {
+            Some(Ok(v)) => Ok(v),
+            _ => Err(format!("{} needs a number", arg)),
+        }
+    }
+}
+
+#[derive(Serialize, Default)]
+struct PhaseReport {
+    phase: String,
+    total_ms: f64,
+    average_ms: f64,
+    max_ms: f64,
+}
+
+impl PhaseReport {
+    fn new(phase: &str) -> Self {
+        Self {
+            phase: phase.to_owned(),
+            ..Default::default()
+        }
+    }
+    fn add(&mut self, time: Duration) {
+        let ms = time.as_secs_f64() * 1000.0;
+        self.total_ms += ms;
+        if ms > self.max_ms {
+            self.max_ms = ms;
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Report {
+    players: usize,
+    ticks: usize,
+    threads: usize,
+    lod: bool,
+    enemies: usize,
+    spawn_ms: f64,
+    tick_average_ms: f64,
+    phases: Vec<PhaseReport>,
+    snapshots: usize,
+    snapshot_average_bytes: f64,
+    snapshot_max_bytes: usize,
+}
+
+// every player walks away from the spawn in its own direction, turns around from time to time
+// and opens the inventory now and then so the snapshots contain the ui as well
+fn scripted_input(player: usize, players: usize, tick: usize) -> ((Float, Float), Vec<String>) {
+    let angle = 360.0 * player as Float / players as Float;
+    let direction = if (tick / 600).is_multiple_of(2) { angle } else { angle + 180.0 };
+    let mouse = vector::point_from_angle(direction);
+    let mut keys = vec!["Space".to_owned()];
+    if tick % 200 < 20 {
+        keys.push("KeyE".to_owned());
+    }
+    ((mouse.0 * 50.0, mouse.1 * 50.0), keys)
+}
+
+fn run(options: &Options, threads: usize) -> Report {
+    let (_sender, receiver) = channel();
+    let start = Instant::now();
+    let mut game = Game::new(receiver);
+    let spawn = start.elapsed();
+    game.threads = threads;
+    game.lod.enabled = options.lod;
+
+    let names: Vec<String> = (0..options.players).map(|p| format!("bench{}", p)).collect();
+    for name in names.iter() {
+        game.players.push(Player::new(name));
+    }
+
+    let mut phases: Vec<PhaseReport> = vec![];
+    let mut pack = PhaseReport::new("pack_objects");
+    let mut bytes = 0;
+    let mut max_bytes = 0;
+    let mut snapshots = 0;
+    let mut tick_total = Duration::ZERO;
+    for tick in 0..options.ticks {
+        for (p, name) in names.iter().enumerate() {
+            let (mouse, keys) = scripted_input(p, names.len(), tick);
+            game.handle_input(name, mouse, keys, 0, None);
+        }
+        let start = Instant::now();
+        game.update();
+        tick_total += start.elapsed();
+        for (phase, time) in game.phases.iter() {
+            match phases.iter_mut().find(|report| report.phase == *phase) {
+                Some(report) => report.add(*time),
+                None => {
+                    let mut report = PhaseReport::new(phase);
+                    report.add(*time);
+                    phases.push(report);
+                },
+            }
+        }
+        for name in names.iter() {
+            let start = Instant::now();
+            let snapshot = game.pack_objects(name);
+            pack.add(start.elapsed());
+            bytes += snapshot.len();
+            max_bytes = max_bytes.max(snapshot.len());
+            snapshots += 1;
+        }
+    }
+    phases.push(pack);
+    for report in phases.iter_mut() {
+        report.average_ms = report.total_ms / options.ticks.max(1) as f64;
+    }
+
+    Report {
+        players: options.players,
+        ticks: options.ticks,
+        threads,
+        lod: options.lod,
+        enemies: game.enemies.iter().map(|group| group.1.len()).sum(),
+        spawn_ms: spawn.as_secs_f64() * 1000.0,
+        tick_average_ms: tick_total.as_secs_f64() * 1000.0 / options.ticks.max(1) as f64,
+        phases,
+        snapshots,
+        snapshot_average_bytes: bytes as f64 / snapshots.max(1) as f64,
+        snapshot_max_bytes: max_bytes,
+    }
+}
+
+fn print(report: &Report) {
+    println!(
+        "{} players, {} ticks, {} thread(s), lod {}",
+        report.players,
+        report.ticks,
+        report.threads,
+        if report.lod { "on" } else { "off" },
+    );
+    println!("  spawn: {:.3}ms, {} enemies at the end", report.spawn_ms, report.enemies);
+    println!("  tick: {:.3}ms average", report.tick_average_ms);
+    for phase in report.phases.iter() {
+        println!("  {:<18} {:>9.3}ms avg {:>9.3}ms max", phase.phase, phase.average_ms, phase.max_ms);
+    }
+    println!(
+        "  snapshots: {}, {:.0} bytes average, {} bytes max",
+        report.snapshots,
+        report.snapshot_average_bytes,
+        report.snapshot_max_bytes,
+    );
+}
+
+fn main() {
+    let options = match Options::parse() {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        },
+    };
+    let mut reports = vec![];
+    if options.compare {
+        reports.push(run(&options, 1));
+    }
+    reports.push(run(&options, options.threads));
+
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+        return;
+    }
+    for report in reports.iter() {
+        print(report);
+    }
+    if reports.len() == 2 {
+        println!("speedup: {:.2}x", reports[0].tick_average_ms / reports[1].tick_average_ms);
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use crate::Float;
+
+pub type Cell = (i64, i64);
+
+// uniform grid that buckets entries by the cells their bounding box touches
+// so only entries sharing a cell have to be compared with each other
+pub struct SpatialHash<T: Copy + Ord> {
+    pub cell_size: Float,
+    pub cells: HashMap<Cell, Vec<(T, (Cell, Cell))>>,
+}
+
+impl<T: Copy + Ord> SpatialHash<T> {
+    pub fn new(cell_size: Float) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+    pub fn cell(&self, position: (Float, Float)) -> Cell {
+        (
+            (position.0 / self.cell_size).floor() as i64,
+            (position.1 / self.cell_size).floor() as i64,
+        )
+    }
+    pub fn insert(&mut self, position: (Float, Float), radius: Float, entry: T) {
+        let min = self.cell((position.0 - radius, position.1 - radius));
+        let max = self.cell((position.0 + radius, position.1 + radius));
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                self.cells.entry((x, y)).or_default().push((entry, (min, max)));
+            }
+        }
+    }
+    // every unordered pair of entries that share at least one cell, sorted so the result is deterministic
+    pub fn pairs(&self) -> Vec<(T, T)> {
+        let mut pairs: Vec<(T, T)> = vec![];
+        for (cell, entries) in self.cells.iter() {
+            for (i, (a, abox)) in entries.iter().enumerate() {
+                for (b, bbox) in entries[i + 1..].iter() {
+                    if a == b {continue;}
+                    // two entries can share many cells, only the first shared cell reports them
+                    let first = (abox.0.0.max(bbox.0.0), abox.0.1.max(bbox.0.1));
+                    if first != *cell {continue;}
+                    if a < b {
+                        pairs.push((*a, *b));
+                    }
+                    else {
+                        pairs.push((*b, *a));
+                    }
+                }
+            }
+        }
+        pairs.sort();
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod broadphase_tests {
+    use std::sync::mpsc::channel;
+
+    use crate::{enemy::Enemy, game::{handle_enemy_collisions, Game}, gametraits::{Moveable, Position}};
+
+    use super::*;
+
+    #[test]
+    fn cell_test() {
+        let hash: SpatialHash<usize> = SpatialHash::new(100.0);
+        assert_eq!(hash.cell((50.0, 150.0)), (0, 1));
+        assert_eq!(hash.cell((-50.0, -150.0)), (-1, -2));
+    }
+
+    #[test]
+    fn pairs_test() {
+        let mut hash: SpatialHash<usize> = SpatialHash::new(100.0);
+        hash.insert((10.0, 10.0), 5.0, 0);
+        hash.insert((20.0, 20.0), 5.0, 1);
+        hash.insert((1000.0, 1000.0), 5.0, 2);
+        // overlaps a lot of cells but must only be reported once per partner
+        hash.insert((50.0, 50.0), 300.0, 3);
+        assert_eq!(hash.pairs(), vec![(0, 1), (0, 3), (1, 3)]);
+    }
+
+    #[test]
+    fn enemy_collision_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        let mut projectile = Enemy::new(5.0, 0.0, (0.0, 0.0), 10.0, "black");
+        projectile.solid = false;
+        game.enemies = vec![
+            (vec![], vec![Enemy::new(0.0, 0.0, (1.0, 0.0), 10.0, "black"), Enemy::new(15.0, 0.0, (-1.0, 0.0), 10.0, "black"), projectile]),
+            (vec![], vec![Enemy::new(0.0, 300.0, (1.0, 0.0), 10.0, "black")]),
+            (vec![], vec![Enemy::new(10.0, 300.0, (-1.0, 0.0), 10.0, "black")]),
+        ];
+        game.enemy_collisions = vec![(0, 0)];
+        // sets the radius multipliers like every tick does before the collisions
+        crate::enemy::handle_effects(&mut game);
+        handle_enemy_collisions(&mut game);
+        // pushed apart and the same mass swaps the velocities
+        let (a, b) = (&game.enemies[0].1[0], &game.enemies[0].1[1]);
+        assert!(b.get_x() - a.get_x() >= 20.0 - 1e-9);
+        assert_eq!((a.get_velocity(), b.get_velocity()), ((-1.0, 0.0), (1.0, 0.0)));
+        // projectiles and groups without a listed pair go through each other
+        assert_eq!(game.enemies[0].1[2].get_x(), 5.0);
+        assert_eq!((game.enemies[1].1[0].get_x(), game.enemies[2].1[0].get_x()), (0.0, 10.0));
+        assert_eq!(game.enemies[1].1[0].get_velocity(), (1.0, 0.0));
+    }
+}
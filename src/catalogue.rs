@@ -0,0 +1,223 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{game::{DrawPack, Game, Shape}, gametraits::Radius, item::{Activation, Charges, Item, ItemEffect, Slot}, Float};
+
+// the catalogue the game starts with, main.rs loads the same file from disk
+const BUILTIN: &str = include_str!("../data/items.json");
+// size of the item icons
+const ICON_SCALE: Float = 0.3;
+
+// where collectables with this item lie when the world spawns
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Placement {
+    pub x: Float,
+    pub y: Float,
+    pub color: (u8, u8, u8),
+    #[serde(default = "one")]
+    pub count: usize,
+    // scatters the collectables around x and y between these distances
+    #[serde(default)]
+    pub spread: Option<(Float, Float)>,
+    // ticks until a picked up collectable comes back, never without
+    #[serde(default)]
+    pub respawn: Option<usize>,
+}
+
+// serde default for counts, loot and saves use it too
+pub fn one() -> usize {
+    1
+}
+
+// a spread needs room between its distances
+pub fn valid_spread(spread: Option<(Float, Float)>) -> bool {
+    spread.map_or(true, |(min, max)| min >= 0.0 && min < max)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ItemType {
+    // stable, saves and admin commands use it
+    pub id: String,
+    // shown in the inventory
+    pub name: String,
+    #[serde(default)]
+    pub icon: Option<String>,
+    // drawn around the player while the item is active
+    #[serde(default)]
+    pub aura: Vec<DrawPack>,
+    #[serde(default)]
+    pub effects: Vec<ItemEffect>,
+    #[serde(default)]
+    pub activation: Activation,
+    // only for triggered items
+    #[serde(default)]
+    pub cooldown: usize,
+    #[serde(default)]
+    pub charges: Option<Charges>,
+    // equipment slot, items without one can all be active at once
+    #[serde(default)]
+    pub slot: Option<Slot>,
+    // how many of them share one place in the inventory
+    #[serde(default = "one")]
+    pub stack: usize,
+    #[serde(default)]
+    pub placements: Vec<Placement>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ItemCatalogue {
+    pub items: Vec<ItemType>,
+}
+
+impl Default for ItemCatalogue {
+    fn default() -> Self {
+        Self::parse(BUILTIN).expect("builtin item catalogue")
+    }
+}
+
+fn positive(radius: &Radius) -> bool {
+    match radius {
+        Radius::Absolute(r) | Radius::Relative(r) => *r > 0.0,
+    }
+}
+
+fn validate_effect(effect: &ItemEffect) -> Result<(), String> {
+    let valid = match effect {
+        ItemEffect::Vision((min, max)) => *min > 0.0 && min <= max,
+        ItemEffect::Speed(s) => *s > 0.0,
+        ItemEffect::SlowEnemies { power, radius, .. } => *power > 0.0 && positive(radius),
+        ItemEffect::ShrinkEnemies { power, radius, duration } => *power > 0.0 && positive(radius) && *duration > 0,
+        ItemEffect::Revive { radius } => positive(radius),
+        ItemEffect::PushEnemies { radius, .. } | ItemEffect::RotateEnemies { radius, .. } => positive(radius),
+        ItemEffect::Harden { .. } => true,
+        ItemEffect::DilateTime { power, radius } => *power > 0.0 && *power <= 1.0 && positive(radius),
+        ItemEffect::GravityWell { power, radius, duration, .. } => *power > 0.0 && *radius > 0.0 && *duration > 0,
+        ItemEffect::SlowZone { power, radius, lifetime, .. } => *power > 0.0 && *radius > 0.0 && *lifetime > 0,
+    };
+    if !valid {
+        return Err(format!("invalid effect {:?}", effect));
+    }
+    Ok(())
+}
+
+impl ItemCatalogue {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let json = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::parse(&json)
+    }
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let items: Vec<ItemType> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let catalogue = Self { items };
+        catalogue.validate()?;
+        Ok(catalogue)
+    }
+    // everything that would break later, while spawning or restoring a save
+    pub fn validate(&self) -> Result<(), String> {
+        let mut ids = HashSet::new();
+        for item in self.items.iter() {
+            if item.id.is_empty() || item.id.contains(char::is_whitespace) {
+                return Err(format!("invalid item id {:?}", item.id));
+            }
+            if !ids.insert(item.id.as_str()) {
+                return Err(format!("item {} exists twice", item.id));
+            }
+            if item.name.is_empty() {
+                return Err(format!("item {} has no name", item.id));
+            }
+            if item.icon.as_deref() == Some("") {
+                return Err(format!("item {} has an empty icon", item.id));
+            }
+            for effect in item.effects.iter() {
+                validate_effect(effect).map_err(|e| format!("item {}: {}", item.id, e))?;
+            }
+            if item.activation != Activation::Triggered && (item.cooldown > 0 || item.charges.is_some()) {
+                return Err(format!("item {} has a cooldown or charges without being triggered", item.id));
+            }
+            if item.activation == Activation::Passive && item.slot.is_some() {
+                return Err(format!("item {} is passive and cant share a slot", item.id));
+            }
+            if item.stack == 0 {
+                return Err(format!("item {} has an empty stack", item.id));
+            }
+            // a stack shares one activation, so only items that are all the same can stack
+            if item.stack > 1 && (item.activation == Activation::Triggered || item.slot.is_some()) {
+                return Err(format!("item {} is triggered or has a slot and cant stack", item.id));
+            }
+            if item.charges.map_or(false, |c| c.max == 0) {
+                return Err(format!("item {} has no charges", item.id));
+            }
+            for placement in item.placements.iter() {
+                if placement.count == 0 {
+                    return Err(format!("item {} has a placement without collectables", item.id));
+                }
+                if placement.respawn == Some(0) {
+                    return Err(format!("item {} has a placement that respawns every tick", item.id));
+                }
+                if !valid_spread(placement.spread) {
+                    return Err(format!("item {} has an empty spread", item.id));
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn get(&self, id: &str) -> Option<&ItemType> {
+        self.items.iter().find(|i| i.id == id)
+    }
+    // ids first, saves from before the catalogue stored display names
+    pub fn find(&self, id_or_name: &str) -> Option<&ItemType> {
+        self.get(id_or_name).or_else(|| self.items.iter().find(|i| i.name == id_or_name))
+    }
+}
+
+impl Game {
+    // builds a new item with a fresh id, None for unknown item types
+    pub fn create_item(&mut self, id: &str) -> Option<Item> {
+        let item_type = self.catalogue.find(id)?;
+        let icon = item_type.icon.as_ref().map(|keyword| {
+            DrawPack::new("", Shape::Image { keyword: keyword.clone(), scale: ICON_SCALE }, (0.0, 0.0))
+        });
+        let mut item = Item::new(&item_type.id, &item_type.name, item_type.effects.clone(), item_type.aura.clone(), &mut self.item_counter, icon);
+        item.activation = item_type.activation;
+        item.cooldown = item_type.cooldown;
+        item.charges = item_type.charges;
+        item.charges_left = item_type.charges.map_or(0, |c| c.max);
+        item.slot = item_type.slot;
+        item.stack = item_type.stack;
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod catalogue_tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+
+    #[test]
+    fn catalogue_test() {
+        let catalogue = ItemCatalogue::default();
+        assert_eq!(catalogue.find("teleportation scroll").unwrap().id, "teleportation_scroll");
+        assert!(catalogue.get("dragonfire rune").is_none());
+
+        let twice = r#"[{"id": "a", "name": "a"}, {"id": "a", "name": "b"}]"#;
+        assert!(ItemCatalogue::parse(twice).is_err());
+        let no_charges = r#"[{"id": "a", "name": "a", "activation": "Triggered", "charges": {"max": 0}}]"#;
+        assert!(ItemCatalogue::parse(no_charges).is_err());
+        let toggle_cooldown = r#"[{"id": "a", "name": "a", "cooldown": 10}]"#;
+        assert!(ItemCatalogue::parse(toggle_cooldown).is_err());
+        let spread = r#"[{"id": "a", "name": "a", "placements": [{"x": 0, "y": 0, "color": [0, 0, 0], "spread": [5, 5]}]}]"#;
+        assert!(ItemCatalogue::parse(spread).is_err());
+        let respawn = r#"[{"id": "a", "name": "a", "placements": [{"x": 0, "y": 0, "color": [0, 0, 0], "respawn": 0}]}]"#;
+        assert!(ItemCatalogue::parse(respawn).is_err());
+
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        let placed: usize = game.catalogue.items.iter().flat_map(|i| i.placements.iter()).map(|p| p.count).sum();
+        let looted: usize = game.loot.spawners.iter().map(|s| s.count).sum();
+        assert_eq!(game.collectables.len(), placed + looted);
+        let heart = game.create_item("heart").unwrap();
+        assert_eq!(heart.kind, "heart");
+        assert!(heart.icon.is_some());
+    }
+}
@@ -14,8 +14,15 @@ pub struct Collectable {
     pub just_collided: bool,
     pub items: Vec<Item>,
     pub old_position: (Float, Float),
+    // index of the spawner that placed it, it respawns there once this is picked up
+    pub spawner: Option<usize>,
+    // ticks until items nobody picked up vanish, None stays forever
+    pub despawn: Option<usize>,
 }
 
+// ticks dropped items lie around
+pub const DESPAWN_TICKS: usize = 18000;
+
 impl_Entity!(Collectable);
 
 impl Collectable {
@@ -36,8 +43,23 @@ impl Collectable {
 
         p
     }
+    // items a player dropped or left behind, they vanish after a while
+    pub fn dropped(x: Float, y: Float, items: Vec<Item>) -> Self {
+        let mut collectable = Self::new(x, y, Color::new(0, 0, 255, 1), items);
+        collectable.despawn = Some(DESPAWN_TICKS);
+        collectable
+    }
+    // moves what fits into the inventory of the player, the rest stays on the ground
     pub fn collect(&mut self, player: &mut Player) {
-        player.inventory.items.append(&mut self.items);
+        let mut left = vec![];
+        for item in self.items.drain(..) {
+            let count = item.count;
+            match player.inventory.add(item) {
+                Ok(()) => player.stats.items_collected += count,
+                Err(item) => left.push(item),
+            }
+        }
+        self.items = left;
     }
 }
 
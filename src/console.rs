@@ -0,0 +1,115 @@
+use std::{io::{self, BufRead}, sync::mpsc::{self, Sender}, thread::{self, JoinHandle}};
+
+use crate::{admin::AdminCommand, server::ServerMessage, Float};
+
+const HELP: &str = "commands:
+  players
+  tp <name> <x> <y>
+  give <name> <item>
+  remove <name> <item>
+  kick <name>
+  kill <name>
+  revive <name>
+  spawn <archetype> <x> <y>
+  respawn <group>
+  speed <multiplier>
+  pause
+  resume
+  step <n>
+  reload
+  shutdown
+  help";
+
+// one line of the console, names are a single word, item names can contain spaces
+pub fn parse_line(line: &str) -> Result<AdminCommand, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let arg = |i: usize| -> Result<String, String> {
+        words.get(i).map(|w| w.to_string()).ok_or(format!("{} needs more arguments, see help", words[0]))
+    };
+    let number = |i: usize| -> Result<Float, String> {
+        arg(i)?.parse::<Float>().map_err(|_| format!("{} is not a number", words[i]))
+    };
+    let rest = |i: usize| -> Result<String, String> {
+        if words.len() <= i {
+            return Err(format!("{} needs more arguments, see help", words[0]));
+        }
+        Ok(words[i..].join(" "))
+    };
+    let command = match words.first() {
+        Some(&"players") => AdminCommand::Players,
+        Some(&"tp") => AdminCommand::Teleport { name: arg(1)?, x: number(2)?, y: number(3)? },
+        Some(&"give") => AdminCommand::Give { name: arg(1)?, item: rest(2)? },
+        Some(&"remove") => AdminCommand::Remove { name: arg(1)?, item: rest(2)? },
+        Some(&"kick") => AdminCommand::Kick { name: arg(1)? },
+        Some(&"kill") => AdminCommand::Kill { name: arg(1)? },
+        Some(&"revive") => AdminCommand::Revive { name: arg(1)? },
+        Some(&"spawn") => AdminCommand::Spawn { archetype: arg(1)?, x: number(2)?, y: number(3)? },
+        Some(&"respawn") => AdminCommand::Respawn { group: arg(1)? },
+        Some(&"speed") => AdminCommand::Speed { multiplier: number(1)? },
+        Some(&"pause") => AdminCommand::Pause,
+        Some(&"resume") => AdminCommand::Resume,
+        Some(&"step") => {
+            let ticks = arg(1).unwrap_or("1".to_owned());
+            AdminCommand::Step { ticks: ticks.parse().map_err(|_| format!("{} is not a tick count", ticks))? }
+        },
+        Some(&"reload") => AdminCommand::Reload,
+        Some(&"shutdown") => AdminCommand::Shutdown,
+        Some(word) => return Err(format!("unknown command {}, see help", word)),
+        None => return Err("".to_owned()),
+    };
+    Ok(command)
+}
+
+// reads commands from stdin and sends them to the game like the admin routes do
+pub fn start(sender: Sender<ServerMessage>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let line = match line {
+                Ok(l) => l,
+                Err(_) => break,
+            };
+            if line.trim() == "help" {
+                println!("{}", HELP);
+                continue;
+            }
+            let command = match parse_line(&line) {
+                Ok(c) => c,
+                Err(e) => {
+                    if !e.is_empty() {
+                        println!("{}", e);
+                    }
+                    continue;
+                },
+            };
+            let (answer_sender, answer_receiver) = mpsc::channel();
+            if sender.send(ServerMessage::Admin(command, answer_sender)).is_err() {
+                println!("game is not running anymore");
+                break;
+            }
+            match answer_receiver.recv() {
+                Ok(Ok(result)) => match serde_json::from_str::<serde_json::Value>(&result) {
+                    Ok(value) => println!("{}", serde_json::to_string_pretty(&value).unwrap()),
+                    Err(_) => println!("{}", result),
+                },
+                Ok(Err(e)) => println!("error: {}", e),
+                Err(_) => println!("game did not answer"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod console_tests {
+    use super::*;
+
+    #[test]
+    fn parse_line_test() {
+        assert_eq!(parse_line("tp jo 10 -20.5"), Ok(AdminCommand::Teleport { name: "jo".to_owned(), x: 10.0, y: -20.5 }));
+        assert_eq!(parse_line("give jo teleportation scroll"), Ok(AdminCommand::Give { name: "jo".to_owned(), item: "teleportation scroll".to_owned() }));
+        assert_eq!(parse_line("  step 5 "), Ok(AdminCommand::Step { ticks: 5 }));
+        assert_eq!(parse_line("step"), Ok(AdminCommand::Step { ticks: 1 }));
+        assert!(parse_line("tp jo ten 3").is_err());
+        assert!(parse_line("spawn snake 1").is_err());
+        assert!(parse_line("fly jo").is_err());
+    }
+}
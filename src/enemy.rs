@@ -1,10 +1,10 @@
 use rand::{thread_rng, Rng};
 
-use crate::{action::Action, game::{DrawPack, Game, Shape}, impl_Drawable, impl_Entity, impl_Moveable, impl_Position, player::PlayerEffect, vector, Float};
+use crate::{action::Action, game::{DrawPack, Game, Shape}, impl_Drawable, impl_Entity, impl_Moveable, impl_Position, parallel::{self, Group}, player::{Player, PlayerEffect}, vector, Float};
 use crate::gametraits::*;
 use crate::{impl_RadiusTrait};
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Enemy {
     pub id: usize,
     pub velocity: (Float, Float),
@@ -18,7 +18,17 @@ pub struct Enemy {
     pub just_collided: bool,
     pub view_radius: Radius,
     pub harmless: bool,
+    // takes part in enemy-enemy collisions
+    pub solid: bool,
+    // set by the lod, see lod.rs
+    pub sleeping: bool,
+    pub lod_skip: bool,
+    pub lod_step: Float,
     pub old_position: (Float, Float),
+    // how fast time runs for this enemy, below 1 inside a time bubble
+    pub time_scale: Float,
+    // collects the scaled time, cooldowns and lifetimes only tick once it is full
+    pub clock: Float,
 }
 
 impl_Entity!(Enemy);
@@ -33,6 +43,9 @@ impl Enemy {
             view_radius: Radius::Relative(1.0),
             draw_packs: vec![],
             speed_multiplier: 1.0,
+            time_scale: 1.0,
+            solid: true,
+            lod_step: 1.0,
             ..Default::default()
         };
         p.draw_packs.push(DrawPack::new(color, Shape::Circle { radius: Radius::Relative(1.0) }, (0.0, 0.0)));
@@ -40,6 +53,10 @@ impl Enemy {
 
         p
     }
+    // false while a slowed enemy skips a tick of its cooldowns and lifetime
+    pub fn ticking(&self) -> bool {
+        self.clock >= 1.0
+    }
 }
 
 #[derive(Clone)]
@@ -57,46 +74,59 @@ pub enum EnemyEffect {
     ShrinkPlayers {radius: Radius, shrink: Float, duration: usize},
 }
 
-pub fn handle_effects(game: &mut Game) {
+// how far something in reach gets pushed away from the middle, negative power pulls it in but never past the middle
+pub fn push(middle: (Float, Float), position: (Float, Float), reach: Float, power: Float) -> Option<(Float, Float)> {
+    let dist = vector::distance(middle, position);
+    if dist.2 > reach {
+        return None;
+    }
+    Some(vector::normalize((dist.0, dist.1), power.max(-dist.2)))
+}
+
+pub fn group_actions(g: usize, group: &Group, players: &[Player]) -> (Vec<(usize, Action)>, Vec<(usize, Action)>) {
     let mut actions: Vec<(usize, Action)> = vec![];
     let mut deletions: Vec<(usize, Action)> = vec![];
-    for (g, group) in game.enemies.iter().enumerate() {
-        for (i, enemy) in group.1.iter().enumerate() {
-            for (e, effect) in enemy.effects.iter().enumerate() {
-                match effect {
-                    EnemyEffect::Chase { radius, power } => {
-                        for player in game.players.iter() {
-                            if !player.alive {continue;}
-                            let dist = vector::distance((enemy.get_x(), enemy.get_y()), (player.get_x(), player.get_y()));
-                            if dist.2 <= radius.translate(enemy.get_radius()) + player.get_radius() {
-                                let add = vector::normalize((dist.0, dist.1), *power);
-                                actions.push((i, Action::UpdateEnemyVelocity(g, (enemy.velocity.0 + add.0, enemy.velocity.1 + add.1))));
-                            }
+    for (i, enemy) in group.1.iter().enumerate() {
+        // sleeping enemies keep their counters running on the ticks they skip, only what needs players waits
+        for (e, effect) in enemy.effects.iter().enumerate() {
+            match effect {
+                EnemyEffect::Chase { radius, power } => {
+                    if enemy.lod_skip {continue;}
+                    for player in players.iter() {
+                        if !player.alive || player.safe {continue;}
+                        let dist = vector::distance((enemy.get_x(), enemy.get_y()), (player.get_x(), player.get_y()));
+                        if dist.2 <= radius.translate(enemy.get_radius()) + player.get_radius() {
+                            let add = vector::normalize((dist.0, dist.1), *power);
+                            actions.push((i, Action::UpdateEnemyVelocity(g, (enemy.velocity.0 + add.0, enemy.velocity.1 + add.1))));
                         }
                     }
-                    EnemyEffect::Crumble => {
-                        if enemy.just_collided {
-                            actions.push((i, Action::SpawnCrumble(g)));
-                        }
-                    },
-                    EnemyEffect::Lifetime(t) => {
+                }
+                EnemyEffect::Crumble => {
+                    if enemy.just_collided {
+                        actions.push((i, Action::SpawnCrumble(g)));
+                    }
+                },
+                EnemyEffect::Lifetime(t) => {
+                    if enemy.ticking() {
                         deletions.push((i, Action::ReduceLifetime { group: g, effect: e }));
-                    },
-                    EnemyEffect::Push { radius, power } => {
-                        for (p, player) in game.players.iter().enumerate() {
-                            if !player.alive {
-                                continue;
-                            }
-                            let dist = vector::distance((enemy.get_x(), enemy.get_y()), (player.get_x(), player.get_y()));
-                            if dist.2 <= radius.translate(enemy.get_radius()) + player.get_radius() {
-                                let add = vector::normalize((dist.0, dist.1), *power);
-                                actions.push((p, Action::AddPlayerPosition(add)));
-                            }
+                    }
+                },
+                EnemyEffect::Push { radius, power } => {
+                    if enemy.lod_skip {continue;}
+                    for (p, player) in players.iter().enumerate() {
+                        if !player.alive || player.safe {
+                            continue;
+                        }
+                        let reach = radius.translate(enemy.get_radius()) + player.get_radius();
+                        if let Some(add) = push((enemy.get_x(), enemy.get_y()), (player.get_x(), player.get_y()), reach, *power) {
+                            actions.push((p, Action::AddPlayerPosition(add)));
                         }
-                    },
-                    EnemyEffect::Shoot { radius, speed, cooldown, time_left, lifetime, projectile_radius, color, effects, under_dps } => {
-                        for player in game.players.iter() {
-                            if !player.alive {continue;}
+                    }
+                },
+                EnemyEffect::Shoot { radius, speed, cooldown, time_left, lifetime, projectile_radius, color, effects, under_dps } => {
+                    if !enemy.lod_skip {
+                        for player in players.iter() {
+                            if !player.alive || player.safe {continue;}
                             let dist = vector::distance((enemy.get_x(), enemy.get_y()), (player.get_x(), player.get_y()));
                             if dist.2 <= radius.translate(enemy.get_radius()) + player.get_radius() {
                                 let v = vector::normalize((dist.0, dist.1), *speed);
@@ -107,128 +137,156 @@ pub fn handle_effects(game: &mut Game) {
                                 break;
                             }
                         }
+                    }
+                    if enemy.ticking() {
                         actions.push((i, Action::ReduceCooldown(g)));
-                    },
-                    EnemyEffect::Explode { lifetime, radius, speed, amount, time_left, cooldown, color, effects, under_dps } => {
-                        if *time_left == 0 {
-                            for _ in 0..*amount {
-                                let v = (rand::thread_rng().gen_range(-*speed..=*speed), rand::thread_rng().gen_range(-*speed..=*speed));
-                                let radius = rand::thread_rng().gen_range(radius.0..=radius.1);
-                                actions.push((i, Action::SpawnProjectile { group: g, velocity: v, radius, color: color.clone(), lifetime: *lifetime, effects: effects.clone(), under_dps: under_dps.clone() }));
-                            }
-                            actions.push((i, Action::ResetCooldown(g)));
+                    }
+                },
+                EnemyEffect::Explode { lifetime, radius, speed, amount, time_left, cooldown, color, effects, under_dps } => {
+                    if *time_left == 0 {
+                        for _ in 0..*amount {
+                            let v = (rand::thread_rng().gen_range(-*speed..=*speed), rand::thread_rng().gen_range(-*speed..=*speed));
+                            let radius = rand::thread_rng().gen_range(radius.0..=radius.1);
+                            actions.push((i, Action::SpawnProjectile { group: g, velocity: v, radius, color: color.clone(), lifetime: *lifetime, effects: effects.clone(), under_dps: under_dps.clone() }));
                         }
+                        actions.push((i, Action::ResetCooldown(g)));
+                    }
+                    if enemy.ticking() {
                         actions.push((i, Action::ReduceCooldown(g)));
-                    },
-                    EnemyEffect::SlowPlayers { radius, slow, duration } => {
-                        for (p, player) in game.players.iter().enumerate() {
-                            if !player.alive {continue;}
-                            let dist = vector::distance((enemy.get_x(), enemy.get_y()), (player.get_x(), player.get_y()));
-                            if dist.2 <= radius.translate(enemy.get_radius()) + player.get_radius() {
-                                let id = enemy.id;
-                                // check if effect of this item id is already applied
-                                let position = player.effects.iter().position(|e| {
-                                    match e {
-                                        PlayerEffect::SpeedAlter { origin, slow, ease } => {
-                                            *origin == id
-                                        },
-                                        _ => {
-                                            false
-                                        }
-                                    }
-                                });
-                                match position {
-                                    Some(e) => {
-                                        let effect = player.effects.get(e).unwrap();
-                                        match effect {
-                                            PlayerEffect::SpeedAlter { origin, slow, ease } => {
-                                                // ease = *duration;
-                                                actions.push((p, Action::SetPlayerEase { effect: e, value: *duration }));
-                                            },
-                                            _ => { }
-                                        };
-                                    },
-                                    None => {
-                                        let effect = crate::player::PlayerEffect::SpeedAlter { slow: *slow, ease: *duration, origin: id };
-                                        actions.push((p, Action::PushPlayerEffect(effect)));
+                    }
+                },
+                EnemyEffect::SlowPlayers { radius, slow, duration } => {
+                    if enemy.lod_skip {continue;}
+                    for (p, player) in players.iter().enumerate() {
+                        if !player.alive || player.safe {continue;}
+                        let dist = vector::distance((enemy.get_x(), enemy.get_y()), (player.get_x(), player.get_y()));
+                        if dist.2 <= radius.translate(enemy.get_radius()) + player.get_radius() {
+                            let id = enemy.id;
+                            // check if effect of this item id is already applied
+                            let position = player.effects.iter().position(|e| {
+                                match e {
+                                    PlayerEffect::SpeedAlter { origin, slow, ease } => {
+                                        *origin == id
                                     },
+                                    _ => {
+                                        false
+                                    }
                                 }
+                            });
+                            match position {
+                                Some(e) => {
+                                    let effect = player.effects.get(e).unwrap();
+                                    match effect {
+                                        PlayerEffect::SpeedAlter { origin, slow, ease } => {
+                                            // ease = *duration;
+                                            actions.push((p, Action::SetPlayerEase { effect: e, value: *duration }));
+                                        },
+                                        _ => { }
+                                    };
+                                },
+                                None => {
+                                    let effect = crate::player::PlayerEffect::SpeedAlter { slow: *slow, ease: *duration, origin: id };
+                                    actions.push((p, Action::PushPlayerEffect(effect)));
+                                },
                             }
                         }
-                    },
-                    EnemyEffect::ShrinkPlayers { radius, shrink, duration } => {
-                        for (p, player) in game.players.iter().enumerate() {
-                            let dist = vector::distance((enemy.get_x(), enemy.get_y()), (player.get_x(), player.get_y()));
-                            if dist.2 <= radius.translate(enemy.get_radius()) + player.get_radius() {
-                                let id = enemy.id;
-                                // check if effect of this item id is already applied
-                                let position = player.effects.iter().position(|e| {
-                                    match e {
-                                        PlayerEffect::Shrink { origin, shrink, ease } => {
-                                            *origin == id
-                                        },
-                                        _ => {
-                                            false
-                                        }
-                                    }
-                                });
-                                match position {
-                                    Some(e) => {
-                                        let effect = player.effects.get(e).unwrap();
-                                        match effect {
-                                            PlayerEffect::Shrink { origin, shrink, ease } => {
-                                                // ease = *duration;
-                                                actions.push((p, Action::SetPlayerEase { effect: e, value: *duration }));
-                                            },
-                                            _ => { }
-                                        };
-                                    },
-                                    None => {
-                                        let effect = PlayerEffect::Shrink { origin: id, shrink: *shrink, ease: *duration };
-                                        actions.push((p, Action::PushPlayerEffect(effect)));
+                    }
+                },
+                EnemyEffect::ShrinkPlayers { radius, shrink, duration } => {
+                    if enemy.lod_skip {continue;}
+                    for (p, player) in players.iter().enumerate() {
+                        if player.safe {continue;}
+                        let dist = vector::distance((enemy.get_x(), enemy.get_y()), (player.get_x(), player.get_y()));
+                        if dist.2 <= radius.translate(enemy.get_radius()) + player.get_radius() {
+                            let id = enemy.id;
+                            // check if effect of this item id is already applied
+                            let position = player.effects.iter().position(|e| {
+                                match e {
+                                    PlayerEffect::Shrink { origin, shrink, ease } => {
+                                        *origin == id
                                     },
+                                    _ => {
+                                        false
+                                    }
                                 }
+                            });
+                            match position {
+                                Some(e) => {
+                                    let effect = player.effects.get(e).unwrap();
+                                    match effect {
+                                        PlayerEffect::Shrink { origin, shrink, ease } => {
+                                            // ease = *duration;
+                                            actions.push((p, Action::SetPlayerEase { effect: e, value: *duration }));
+                                        },
+                                        _ => { }
+                                    };
+                                },
+                                None => {
+                                    let effect = PlayerEffect::Shrink { origin: id, shrink: *shrink, ease: *duration };
+                                    actions.push((p, Action::PushPlayerEffect(effect)));
+                                },
                             }
                         }
-                    },
-                    EnemyEffect::Grow { size, maxsize, defaultsize } => {
-                        if enemy.just_collided {
-                            actions.push((i, Action::SetEnemyRadius(g, *defaultsize)));
-                        }
-                        else if enemy.radius < *maxsize {
-                            actions.push((i, Action::SetEnemyRadius(g, enemy.radius + *size)));
-                        }
-                    },
-                    EnemyEffect::SpeedAlter { power, ease, origin } => {
-                        if *ease == 0 {
-                            // remove this effect
-                            deletions.push((i, Action::RemoveEnemyEffect { group: g, effect: e }));
-                        }
-                        else {
-                            deletions.push((i, Action::DecrementEnemyEase { group: g, effect: e }));
-                            actions.push((i, Action::MulEnemySpeedMultiplier { group: g, f: *power }));
-                        }
-                    },
-                    EnemyEffect::Shrink { power, ease, origin, start } => {
-                        if *ease == 0 {
-                            // remove this effect
-                            deletions.push((i, Action::RemoveEnemyEffect { group: g, effect: e }));
-                        }
-                        else {
-                            deletions.push((i, Action::DecrementEnemyEase { group: g, effect: e }));
-                            let r = *power + *power * ((*start - *ease) as Float / *start as Float);
-                            actions.push((i, Action::MulEnemyRadiusMultiplier { f: r, group: g }));
-                        }
-                    },
-                }
+                    }
+                },
+                EnemyEffect::Grow { size, maxsize, defaultsize } => {
+                    if enemy.just_collided {
+                        actions.push((i, Action::SetEnemyRadius(g, *defaultsize)));
+                    }
+                    else if enemy.radius < *maxsize {
+                        actions.push((i, Action::SetEnemyRadius(g, enemy.radius + *size)));
+                    }
+                },
+                EnemyEffect::SpeedAlter { power, ease, origin } => {
+                    if *ease == 0 {
+                        // remove this effect
+                        deletions.push((i, Action::RemoveEnemyEffect { group: g, effect: e }));
+                    }
+                    else {
+                        deletions.push((i, Action::DecrementEnemyEase { group: g, effect: e }));
+                        actions.push((i, Action::MulEnemySpeedMultiplier { group: g, f: *power }));
+                    }
+                },
+                EnemyEffect::Shrink { power, ease, origin, start } => {
+                    if *ease == 0 {
+                        // remove this effect
+                        deletions.push((i, Action::RemoveEnemyEffect { group: g, effect: e }));
+                    }
+                    else {
+                        deletions.push((i, Action::DecrementEnemyEase { group: g, effect: e }));
+                        let r = *power + *power * ((*start - *ease) as Float / *start as Float);
+                        actions.push((i, Action::MulEnemyRadiusMultiplier { f: r, group: g }));
+                    }
+                },
             }
         }
     }
+    (actions, deletions)
+}
+
+pub fn handle_effects(game: &mut Game) {
+    let mut actions: Vec<(usize, Action)> = vec![];
+    let mut deletions: Vec<(usize, Action)> = vec![];
+    // the time bubbles of the last tick decide if cooldowns and lifetimes run this tick
+    for group in game.enemies.iter_mut() {
+        for enemy in group.1.iter_mut() {
+            enemy.clock += enemy.time_scale;
+        }
+    }
+    // turning effects into actions only reads the world so every group can do it on its own thread
+    let players = &game.players;
+    let results = parallel::map_groups(&game.enemies, game.threads, |g, group| group_actions(g, group, players));
+    for (mut a, mut d) in results {
+        actions.append(&mut a);
+        deletions.append(&mut d);
+    }
     // reset enemy speed multiplier to 1.0
     for group in game.enemies.iter_mut() {
         for enemy in group.1.iter_mut() {
             enemy.speed_multiplier = 1.0;
             enemy.radius_multiplier = 1.0;
+            enemy.clock = enemy.clock.fract();
+            enemy.time_scale = 1.0;
         }
     }
     // reverse order due to deletions and index errors
@@ -1,14 +1,17 @@
-use std::{collections::HashMap, sync::mpsc::{Receiver, Sender}, thread::{self, JoinHandle}, time::Duration};
+use std::{collections::HashMap, path::PathBuf, sync::mpsc::{Receiver, Sender}, thread::{self, JoinHandle}, time::{Duration, Instant}};
 
-use crate::{action::Action, collectable::{self, Collectable}, color::{self, Color}, enemy::Enemy, gametraits::{Drawable, EntityIndex, Moveable, Position, Radius}, player::Player, server::ServerMessage, vector::{self, get_intersection, Line}, wall::{Wall, WallType}, Float};
+use crate::{accounts::{Accounts, Login, LogoutDrop}, action::Action, catalogue::ItemCatalogue, admin::AdminCommand, broadphase::SpatialHash, collectable::{self, Collectable}, color::{self, Color}, enemy::Enemy, lod::Lod, loot::{Loot, Spawner}, metrics::Metrics, persistence::SavedPlayer, parallel::{self, Group}, profiler::{PhaseTimer, Phases}, gametraits::{Drawable, EntityIndex, Moveable, Position, Radius}, input::{Controls, InputAction}, inventory::{Filter, HOTBAR_SLOTS, VISIBLE_ROWS}, item::SCROLL, player::Player, revive::ReviveSettings, server::{Outgoing, ServerMessage, StopHandle}, string::StringOperations, trade::Trade, vector::{self, get_intersection, Line}, wall::{Wall, WallType}, waypoint::{Waypoint, WAYPOINT_ROWS}, worldobject::WorldObject, Float};
 use crate::gametraits::*;
 use crate::{impl_RadiusTrait, impl_Drawable, impl_Entity, impl_Moveable, impl_Position};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 pub fn move_object<T: Moveable>(object: &mut T, walls: &Walls, walltypes: Option<&Vec<WallType>>) {
+    move_object_scaled(object, 1.0);
+}
+pub fn move_object_scaled<T: Moveable>(object: &mut T, scale: Float) {
     let (vx, vy) = object.get_velocity();
-    let x = object.get_x() + vx * object.get_speed_multiplier();
-    let y = object.get_y() + vy * object.get_speed_multiplier();
+    let x = object.get_x() + vx * object.get_speed_multiplier() * scale;
+    let y = object.get_y() + vy * object.get_speed_multiplier() * scale;
     object.set_pos(x, y);
 }
 pub fn get_player<'a>(game: &'a mut Game, player: usize) -> &'a mut Player {
@@ -46,7 +49,7 @@ pub fn distance<T: Position, B: Position>(a: &T, b: &B) -> (Float, Float, Float)
     vector::distance(a, b)
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Shape {
     Circle{radius: Radius},
     Rectangle{width: Float, height: Float},
@@ -61,7 +64,7 @@ impl Default for Shape {
         Self::Circle { radius: Radius::Relative(1.0) }
     }
 }
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DrawPack {
     pub color: String,
     pub shape: Shape,
@@ -79,6 +82,7 @@ impl DrawPack {
 
 pub type Walls = Vec<(WallType, Vec<Wall>)>;
 pub type Enemies = Vec<(Vec<WallType>, Vec<Enemy>)>;
+pub type Connections = Vec<(String, Sender<Outgoing>)>;
 
 pub struct Game {
     pub receiver: Receiver<ServerMessage>,
@@ -86,16 +90,57 @@ pub struct Game {
     pub players: Vec<Player>,
     pub game_loop: Option<JoinHandle<()>>,
     pub running: bool,
+    // stops the server from accepting connections before the shutdown drains the messages
+    pub listener: Option<StopHandle>,
     pub enemies: Enemies,
+    // spawner name of every group in enemies, same order
+    pub enemy_groups: Vec<String>,
     pub grid: Vec<((Float, Float), DrawPack, bool)>,
     pub map: Vec<((Float, Float), DrawPack)>,
     pub walls: Walls,
     pub collectables: Vec<Collectable>,
+    // pairs of enemy group indices that bounce off each other, (g, g) for collisions inside one group
+    pub enemy_collisions: Vec<(usize, usize)>,
+    pub tick: usize,
+    pub lod: Lod,
+    // threads used for the per group work, 1 runs everything on the game thread
+    pub threads: usize,
+    // how long every phase of the last tick took
+    pub phases: Phases,
+    pub metrics: Metrics,
+    // next free item id
+    pub item_counter: usize,
+    // every item type, collectables are placed from it
+    pub catalogue: ItemCatalogue,
+    // a paused game still handles messages and sends snapshots but doesnt tick
+    pub paused: bool,
+    // ticks per frame, fractions carry over to the next frame
+    pub speed: Float,
+    tick_budget: Float,
+    // players saved on the last shutdown that didnt log in again yet
+    pub saved_players: HashMap<String, SavedPlayer>,
+    pub save_path: Option<PathBuf>,
+    pub accounts: Accounts,
+    pub logout_drop: LogoutDrop,
+    // outlines of the map areas for discovering them
+    pub areas: Vec<(WallType, Vec<(Float, Float)>)>,
+    pub revive: ReviveSettings,
+    pub waypoints: Vec<Waypoint>,
+    // teleportation scrolls a teleport to a waypoint costs
+    pub teleport_cost: usize,
+    // traps and wells placed by items
+    pub world_objects: Vec<WorldObject>,
+    // requests and open trades between players
+    pub trades: Vec<Trade>,
+    // loot tables and the spawners of random collectables
+    pub loot: Loot,
+    // where collectables come back, built from the catalogue and the loot
+    pub spawners: Vec<Spawner>,
 }
 
-pub fn handle_players(players: &mut Vec<Player>, collectables: &mut Vec<Collectable>) {
+pub fn handle_players(players: &mut Vec<Player>, collectables: &mut Vec<Collectable>, item_counter: &mut usize) {
     for player in players.iter_mut() {
-        player.handle_keys(collectables);
+        player.handle_keys(collectables, item_counter);
         if player.alive {
             player.draw_packs[0].color = player.color.clone();
         }
@@ -105,29 +150,41 @@ pub fn handle_players(players: &mut Vec<Player>, collectables: &mut Vec<Collecta
     }
 }
 pub fn handle_collectables(game: &mut Game) {
-    let mut rems: Vec<usize> = vec![];
     for p in game.players.iter_mut() {
-        for (i, c) in game.collectables.iter_mut().enumerate() {
+        for c in game.collectables.iter_mut() {
             let dist = distance(p, c);
             if dist.2 <= p.get_radius() + c.get_radius() {
                 c.collect(p);
-                rems.push(i);
             }
         }
     }
-    for r in rems.iter().rev() {
-        game.collectables.remove(*r);
+    for c in game.collectables.iter_mut() {
+        if let Some(ticks) = &mut c.despawn {
+            *ticks = ticks.saturating_sub(1);
+        }
+    }
+    // collectables a full inventory couldnt take stay
+    game.collectables.retain(|c| !c.items.is_empty() && c.despawn != Some(0));
+}
+// remembers every map area a player has been in
+pub fn handle_discovery(game: &mut Game) {
+    for player in game.players.iter_mut() {
+        let position = (player.get_x(), player.get_y());
+        for (walltype, corners) in game.areas.iter() {
+            let name = format!("{:?}", walltype);
+            if player.discovered.contains(&name) || !vector::point_in_polygon(position, corners) {continue;}
+            player.discovered.push(name);
+        }
     }
 }
 // player enemy collision
 pub fn handle_kill_revive(game: &mut Game) {
     let mut deaths: Vec<usize> = vec![];
-    let mut revives: Vec<usize> = vec![];
     // handle deaths
     for (i, player) in game.players.iter().enumerate() {
         for group in game.enemies.iter() {
             for enemy in group.1.iter() {
-                if enemy.harmless || player.invincible {
+                if enemy.harmless || enemy.sleeping || player.invincible {
                     continue;
                 }
                 let dd = distance(player, enemy).2;
@@ -139,23 +196,13 @@ pub fn handle_kill_revive(game: &mut Game) {
     }
     for i in deaths {
         let player = game.players.get_mut(i).unwrap();
+        if player.alive {
+            player.stats.deaths += 1;
+        }
         player.alive = false;
     }
-
     // handle revives later so new deaths are accounted
-    for (i, player) in game.players.iter().enumerate() {
-        for other in game.players.iter() {
-            if std::ptr::eq(player, other) || !other.alive {continue;}
-            let dd = distance(player, other).2;
-            if dd <= (player.get_radius() + other.get_radius()) {
-                revives.push(i);
-            }
-        }
-    }
-    for i in revives {
-        let player = game.players.get_mut(i).unwrap();
-        player.alive = true;
-    }
+    crate::revive::handle_revives(game);
 }
 
 pub fn cross_barrier_check<T: Moveable>(object: &T, wall: &Wall) -> Option<Float> {
@@ -179,86 +226,88 @@ pub fn cross_barrier_check<T: Moveable>(object: &T, wall: &Wall) -> Option<Float
     }
 }
 
-pub fn handle_collision(game: &mut Game) {
-    for group in game.enemies.iter_mut() {
-        for enemy in group.1.iter_mut() {
-            enemy.just_collided = false;
-        }
+// handle wall angle from collision
+pub fn bounce(object: &mut dyn Moveable, collision_point: (Float, Float)) {
+    // offset for pushing object away on collision so collision doesnt trigger again
+    const OFFSET: Float = 0.001;
+
+    // push object out of wall
+    let push_vector = Line::from_points(collision_point, (object.get_x(), object.get_y()));
+    let np = push_vector.point(0.0, object.get_radius());
+    object.set_pos(np.0, np.1);
+
+    // calculate angle
+    let (mut x, mut y) = (object.get_x(), object.get_y());
+    let v = object.get_velocity();
+    let speed = vector::abs(v);
+    let dist = vector::distance(collision_point, (x, y));
+    if dist.2 == 0.0 {
+        return;
     }
-    let mut collisions: HashMap<EntityIndex, (Float, Float)> = HashMap::new();
-    let mut barrier_crosses: HashMap<(usize, usize), (Float, Line, Wall)> = HashMap::new();
-    for wgroup in game.walls.iter() {
+    let push = vector::normalize((dist.0, dist.1), object.get_radius() + OFFSET);
+    let nx = collision_point.0 + push.0;
+    let ny = collision_point.1 + push.1;
+    object.set_pos(nx, ny);
+    let new_v = vector::normalize(vector::collision((x, y), v, collision_point), speed);
+    object.set_velocity(new_v);
+    object.set_just_collided(true);
+}
+
+// wall collisions of one enemy group, only touches the group itself so groups can run in parallel
+pub fn handle_group_collision(group: &mut Group, walls: &Walls) {
+    for enemy in group.1.iter_mut() {
+        if enemy.lod_skip {continue;}
+        enemy.just_collided = false;
+    }
+    let mut collisions: HashMap<usize, (Float, Float)> = HashMap::new();
+    let mut barrier_crosses: HashMap<usize, (Float, Line, Wall)> = HashMap::new();
+    for wgroup in walls.iter() {
+        if !group.0.contains(&wgroup.0) && !wgroup.0.is_safe_zone() {continue;}
         for wall in wgroup.1.iter() {
-            // enemies
-            if wall.enemy {
-                for (g, egroup) in game.enemies.iter().enumerate() {
-                    if !egroup.0.contains(&wgroup.0) {continue;} 
-                    for (e, enemy) in egroup.1.iter().enumerate() {
-                        let enemy_vector = Line::from_points(enemy.old_position, (enemy.get_x(), enemy.get_y()));
-                        let moved_distance = vector::abs(enemy_vector.dir);
-                        let clostest = wall.get_nearest_point(&(enemy.get_x(), enemy.get_y()));
-                        let mut barrier_crossed = false;
-
-                        // barrier cross check with vector intersection
-                        if vector::distance(clostest, (enemy.get_x(), enemy.get_y())).2 <= moved_distance {
-                            match cross_barrier_check(enemy, wall) {
-                                Some(ncp) => {
-                                    barrier_crossed = true;
-                                    let other_cross_point = barrier_crosses.get_mut(&(e, g));
-                                    match other_cross_point {
-                                        Some(ocp) => {
-                                            if ncp < ocp.0 {
-                                                ocp.0 = ncp;
-                                            }
-                                        },
-                                        None => {
-                                            // no shorter distance found
-                                            barrier_crosses.insert((e, g), (ncp, enemy_vector, wall.clone()));
-                                        },
+            if !wall.enemy {continue;}
+            for (e, enemy) in group.1.iter().enumerate() {
+                if enemy.lod_skip {continue;}
+                let enemy_vector = Line::from_points(enemy.old_position, (enemy.get_x(), enemy.get_y()));
+                let moved_distance = vector::abs(enemy_vector.dir);
+                let clostest = wall.get_nearest_point(&(enemy.get_x(), enemy.get_y()));
+                let mut barrier_crossed = false;
+
+                // barrier cross check with vector intersection
+                if vector::distance(clostest, (enemy.get_x(), enemy.get_y())).2 <= moved_distance {
+                    match cross_barrier_check(enemy, wall) {
+                        Some(ncp) => {
+                            barrier_crossed = true;
+                            let other_cross_point = barrier_crosses.get_mut(&e);
+                            match other_cross_point {
+                                Some(ocp) => {
+                                    if ncp < ocp.0 {
+                                        ocp.0 = ncp;
                                     }
                                 },
                                 None => {
+                                    // no shorter distance found
+                                    barrier_crosses.insert(e, (ncp, enemy_vector, wall.clone()));
                                 },
-                            };
-                        }
-                        if !barrier_crossed {
-                            // closest point from enemy to walls
-                            if vector::distance(clostest, (enemy.get_x(), enemy.get_y())).2 <= enemy.get_radius() {
-                                let ocp = collisions.get_mut(&EntityIndex::Enemy { g, e });
-                                match ocp {
-                                    Some(ocp) => {
-                                        let old_dist = vector::distance((enemy.x, enemy.y), *ocp);
-                                        let dist = vector::distance((enemy.x, enemy.y), clostest);
-                                        if dist.2 < old_dist.2 {
-                                            *ocp = clostest;
-                                        }
-                                    },
-                                    None => {
-                                        collisions.insert(EntityIndex::Enemy { g, e }, clostest);
-                                    },
-                                }
                             }
-                        }
-                    }
+                        },
+                        None => {
+                        },
+                    };
                 }
-            }
-            // players
-            if wall.player {
-                for (p, player) in game.players.iter().enumerate() {
-                    // cross_barrier_check(player, wall);
-                    let cp = wall.get_nearest_point(&(player.get_x(), player.get_y()));
-                    if vector::distance(cp, (player.get_x(), player.get_y())).2 <= player.get_radius() {
-                        let collision = collisions.get_mut(&EntityIndex::Player { p });
-                        match collision {
+                if !barrier_crossed {
+                    // closest point from enemy to walls
+                    if vector::distance(clostest, (enemy.get_x(), enemy.get_y())).2 <= enemy.get_radius() {
+                        let ocp = collisions.get_mut(&e);
+                        match ocp {
                             Some(ocp) => {
-                                let old_dist = vector::distance((player.x, player.y), *ocp);
-                                let dist = vector::distance((player.x, player.y), cp);
+                                let old_dist = vector::distance((enemy.x, enemy.y), *ocp);
+                                let dist = vector::distance((enemy.x, enemy.y), clostest);
                                 if dist.2 < old_dist.2 {
-                                    *ocp = cp;
+                                    *ocp = clostest;
                                 }
                             },
                             None => {
-                                collisions.insert(EntityIndex::Player { p }, cp);
+                                collisions.insert(e, clostest);
                             },
                         }
                     }
@@ -267,49 +316,112 @@ pub fn handle_collision(game: &mut Game) {
         }
     }
 
-    for ((e, g), (f, line, wall)) in barrier_crosses.iter() {
-        let enemy = get_enemy(game, *g, *e);
+    for (e, (f, line, wall)) in barrier_crosses.iter() {
+        let enemy = group.1.get_mut(*e).unwrap();
         let np = line.point(*f, -enemy.get_radius());
         let cp = wall.get_nearest_point(&np);
         let x = np.0;
         let y = np.1;
         enemy.set_pos(x, y);
-        collisions.insert(EntityIndex::Enemy { g: *g, e: *e }, cp);
+        collisions.insert(*e, cp);
     }
 
-    // handle wall angle from collision
-    // offset for pushing object away on collision so collision doesnt trigger again
-    const OFFSET: Float = 0.001;
-    for (entity_index, collision_point) in collisions {
-        let object: Box<&mut dyn Moveable> = match entity_index {
-            EntityIndex::Player { p } => {
-                Box::new(get_player(game, p))
-            },
-            EntityIndex::Enemy { g, e } => {
-                Box::new(get_enemy(game, g, e))
-            },
+    for (e, collision_point) in collisions {
+        bounce(group.1.get_mut(e).unwrap(), collision_point);
+    }
+}
+
+pub fn handle_player_collision(game: &mut Game) {
+    let mut collisions: HashMap<usize, (Float, Float)> = HashMap::new();
+    for wgroup in game.walls.iter() {
+        for wall in wgroup.1.iter() {
+            if !wall.player {continue;}
+            for (p, player) in game.players.iter().enumerate() {
+                // cross_barrier_check(player, wall);
+                let cp = wall.get_nearest_point(&(player.get_x(), player.get_y()));
+                if vector::distance(cp, (player.get_x(), player.get_y())).2 <= player.get_radius() {
+                    let collision = collisions.get_mut(&p);
+                    match collision {
+                        Some(ocp) => {
+                            let old_dist = vector::distance((player.x, player.y), *ocp);
+                            let dist = vector::distance((player.x, player.y), cp);
+                            if dist.2 < old_dist.2 {
+                                *ocp = cp;
+                            }
+                        },
+                        None => {
+                            collisions.insert(p, cp);
+                        },
+                    }
+                }
+            }
+        }
+    }
+    for (p, collision_point) in collisions {
+        bounce(get_player(game, p), collision_point);
+    }
+}
+
+pub fn handle_collision(game: &mut Game) {
+    let walls = &game.walls;
+    parallel::for_each_group(&mut game.enemies, game.threads, |_, group| handle_group_collision(group, walls));
+    handle_player_collision(game);
+}
+// elastic collisions between enemies of groups listed in enemy_collisions
+// runs before the wall collision so enemies pushed into a wall still get caught by the barrier check
+pub fn handle_enemy_collisions(game: &mut Game) {
+    if game.enemy_collisions.is_empty() {
+        return;
+    }
+    const CELL_SIZE: Float = 200.0;
+    let mut hash: SpatialHash<(usize, usize)> = SpatialHash::new(CELL_SIZE);
+    for (g, group) in game.enemies.iter().enumerate() {
+        if !game.enemy_collisions.iter().any(|(a, b)| {*a == g || *b == g}) {continue;}
+        for (e, enemy) in group.1.iter().enumerate() {
+            if !enemy.solid || enemy.harmless || enemy.lod_skip {continue;}
+            hash.insert((enemy.get_x(), enemy.get_y()), enemy.get_radius(), (g, e));
+        }
+    }
+    for ((ga, ea), (gb, eb)) in hash.pairs() {
+        let allowed = game.enemy_collisions.iter().any(|pair| {*pair == (ga, gb) || *pair == (gb, ga)});
+        if !allowed {continue;}
+        let a = get_enemy(game, ga, ea);
+        let (pa, va, ra) = ((a.get_x(), a.get_y()), a.get_velocity(), a.get_radius());
+        let b = get_enemy(game, gb, eb);
+        let (pb, vb, rb) = ((b.get_x(), b.get_y()), b.get_velocity(), b.get_radius());
+
+        let dist = vector::distance(pa, pb);
+        let overlap = ra + rb - dist.2;
+        if overlap <= 0.0 {continue;}
+        // normal pointing from a to b
+        let n = if dist.2 == 0.0 {
+            (1.0, 0.0)
+        }
+        else {
+            vector::normalize((dist.0, dist.1), 1.0)
         };
+        // heavier enemies get pushed less, mass grows with the area
+        let ma = ra * ra;
+        let mb = rb * rb;
+        let total = ma + mb;
+        let pa = (pa.0 - n.0 * overlap * mb / total, pa.1 - n.1 * overlap * mb / total);
+        let pb = (pb.0 + n.0 * overlap * ma / total, pb.1 + n.1 * overlap * ma / total);
+        let mut va = va;
+        let mut vb = vb;
+        // only exchange momentum if they are moving towards each other
+        let relative = (vb.0 - va.0) * n.0 + (vb.1 - va.1) * n.1;
+        if relative < 0.0 {
+            let j = 2.0 * relative / total;
+            va = (va.0 + n.0 * j * mb, va.1 + n.1 * j * mb);
+            vb = (vb.0 - n.0 * j * ma, vb.1 - n.1 * j * ma);
+        }
 
-        // push object out of wall
-        let push_vector = Line::from_points(collision_point, (object.get_x(), object.get_y()));
-        let np = push_vector.point(0.0, object.get_radius());
-        object.set_pos(np.0, np.1);
-
-        // calculate angle
-        let (mut x, mut y) = (object.get_x(), object.get_y());
-        let v = object.get_velocity();
-        let speed = vector::abs(v);
-        let dist = vector::distance(collision_point, (x, y));
-        if dist.2 == 0.0 {
-            return;
-        }
-        let push = vector::normalize((dist.0, dist.1), object.get_radius() + OFFSET);
-        let nx = collision_point.0 + push.0;
-        let ny = collision_point.1 + push.1;
-        object.set_pos(nx, ny);
-        let new_v = vector::normalize(vector::collision((x, y), v, collision_point), speed);
-        object.set_velocity(new_v);
-        object.set_just_collided(true);
+        let a = get_enemy(game, ga, ea);
+        a.set_pos(pa.0, pa.1);
+        a.set_velocity(va);
+        let b = get_enemy(game, gb, eb);
+        b.set_pos(pb.0, pb.1);
+        b.set_velocity(vb);
     }
 }
 pub fn handle_movements(game: &mut Game) {
@@ -317,31 +429,65 @@ pub fn handle_movements(game: &mut Game) {
         if player.alive && !player.skip_move {
             player.old_position = (player.get_x(), player.get_y());
             move_object(player, &game.walls, None);
+            player.stats.distance += vector::distance(player.old_position, (player.get_x(), player.get_y())).2;
         }
         else {
             player.skip_move = false;
         }
     }
-    for group in game.enemies.iter_mut() {
+    parallel::for_each_group(&mut game.enemies, game.threads, |_, group| {
         for enemy in group.1.iter_mut() {
+            if enemy.lod_skip {continue;}
             enemy.old_position = (enemy.get_x(), enemy.get_y());
-            move_object(enemy, &game.walls, Some(&group.0));
+            move_object_scaled(enemy, enemy.lod_step);
         }
-    }
+    });
 }
 
 impl Game {
+    // the world of the catalogue and loot built into the binary
     pub fn new(receiver: Receiver<ServerMessage>) -> Game {
+        let catalogue = ItemCatalogue::default();
+        let loot = Loot::builtin(&catalogue);
+        Self::with_items(receiver, catalogue, loot)
+    }
+    // the world is only spawned once, with the items that were loaded
+    pub fn with_items(receiver: Receiver<ServerMessage>, catalogue: ItemCatalogue, loot: Loot) -> Game {
         let mut g = Game {
             game_loop: None,
             running: false,
+            listener: None,
             receiver,
             players: Default::default(),
             enemies: Default::default(),
+            enemy_groups: Default::default(),
             grid: Default::default(),
             map: Default::default(),
             walls: Default::default(),
             collectables: Default::default(),
+            enemy_collisions: Default::default(),
+            tick: 0,
+            lod: Default::default(),
+            threads: parallel::default_threads(),
+            phases: Default::default(),
+            metrics: Default::default(),
+            item_counter: 0,
+            catalogue,
+            paused: false,
+            speed: 1.0,
+            tick_budget: 0.0,
+            saved_players: Default::default(),
+            save_path: None,
+            accounts: Default::default(),
+            logout_drop: LogoutDrop::Everything,
+            areas: Default::default(),
+            revive: Default::default(),
+            waypoints: vec![],
+            teleport_cost: 1,
+            world_objects: vec![],
+            trades: vec![],
+            loot,
+            spawners: vec![],
         };
         g.spawn_enemies();
         g.spawn_map();
@@ -349,52 +495,162 @@ impl Game {
 
         g
     }
-    pub fn start(mut self) {
+    // throws away the world and spawns it again, players stay where they are
+    pub fn reload(&mut self) {
+        self.enemies.clear();
+        self.enemy_groups.clear();
+        self.enemy_collisions.clear();
+        self.grid.clear();
+        self.map.clear();
+        self.walls.clear();
+        self.areas.clear();
+        self.waypoints.clear();
+        self.world_objects.clear();
+        self.collectables.clear();
+        self.spawn_enemies();
+        self.spawn_map();
+        self.spawn_collectables();
+    }
+    fn handle_message(&mut self, message: ServerMessage, connections: &mut Connections) {
+        match message {
+            ServerMessage::PasswordHash(name, sender) => {
+                let _ = sender.send(self.accounts.password_hash(&name));
+            },
+            ServerMessage::Login(name, proof, sender) => {
+                if self.players.iter().any(|p| {p.name == name}) {
+                    let _ = sender.send(Outgoing::Close("this name is already playing".to_owned()));
+                    return;
+                }
+                let login = match self.accounts.authenticate(&name, &proof) {
+                    Ok(l) => l,
+                    Err(reason) => {
+                        let _ = sender.send(Outgoing::Close(reason));
+                        return;
+                    },
+                };
+                let mut player = Player::new(&name);
+                match login {
+                    Login::Guest => self.restore_player(&mut player),
+                    Login::Account { token, created } => {
+                        player.account = true;
+                        let saved = self.accounts.accounts.get(&name).and_then(|a| a.player.clone());
+                        if let Some(saved) = saved {
+                            self.apply_saved(&mut player, saved);
+                        }
+                        if created {
+                            println!("account {} created", name);
+                        }
+                        // the client keeps the token for the next login
+                        let _ = sender.send(Outgoing::Frame(serde_json::json!({"token": token}).to_string()));
+                    },
+                }
+                player.stats.logins += 1;
+                crate::revive::make_invulnerable(&mut player, self.revive.spawn_invulnerable_ticks);
+                let _ = sender.send(Outgoing::Frame(serde_json::json!({"controls": player.controls}).to_string()));
+                self.players.push(player);
+                connections.push((name, sender));
+            },
+            ServerMessage::Logout(name) => {
+                self.logout(&name);
+                let r = connections.iter().position(|e| {e.0 == name});
+                match r {
+                    Some(i) => {
+                        connections.remove(i);
+                    },
+                    None => {
+                        println!("tried to delete connection, but connection wasnt there");
+                    },
+                }
+            },
+            ServerMessage::Input { name, mouse, keys, wheel, movement } => {
+                self.handle_input(&name, mouse, keys, wheel, movement);
+            },
+            ServerMessage::Metrics(sender) => {
+                self.metrics.connections = connections.len();
+                let _ = sender.send(self.metrics.render(self));
+            },
+            ServerMessage::Bind { name, key, action } => {
+                let player = match self.get_mut(&name) {
+                    Some(p) => p,
+                    None => return,
+                };
+                let result = match (key, action) {
+                    (Some(key), Some(action)) => player.controls.bind(&key, action),
+                    (Some(key), None) => {
+                        player.controls.unbind(&key);
+                        Ok(())
+                    },
+                    (None, _) => {
+                        player.controls = Controls::default();
+                        Ok(())
+                    },
+                };
+                if let Err(e) = result {
+                    println!("binding for {} was ignored: {}", name, e);
+                }
+                // the client shows the bindings it got last
+                let controls = serde_json::json!({"controls": player.controls}).to_string();
+                if let Some((_, sender)) = connections.iter().find(|c| {c.0 == name}) {
+                    let _ = sender.send(Outgoing::Frame(controls));
+                }
+            },
+            ServerMessage::Leaderboard(sender) => {
+                let _ = sender.send(self.leaderboard_json().to_string());
+            },
+            ServerMessage::Admin(command, sender) => {
+                let result = crate::admin::execute(self, &command);
+                match (&command, &result) {
+                    (AdminCommand::Kick { name }, Ok(_)) => {
+                        if let Some(i) = connections.iter().position(|c| {c.0 == *name}) {
+                            let _ = connections[i].1.send(Outgoing::Close("kicked".to_owned()));
+                            connections.remove(i);
+                        }
+                    },
+                    _ => {},
+                }
+                let _ = sender.send(result.map(|r| r.to_string()));
+            },
+        }
+    }
+    // runs the game loop until running is cleared by the admin api or a shutdown signal,
+    // the handle finishes after the players are saved and every client got a close frame
+    pub fn start(mut self) -> JoinHandle<()> {
         self.running = true;
-        let t = thread::spawn(move || {
-            let mut connections: Vec<(String, Sender<String>)> = vec![];
+        thread::spawn(move || {
+            let mut connections: Connections = vec![];
+            // ticks between the tick time reports
+            const REPORT_INTERVAL: usize = 1000;
+            let mut tick_time = Duration::ZERO;
             loop {
+                if crate::shutdown::requested() {
+                    self.running = false;
+                }
                 // handle all messages via loop
                 loop {
                     match self.receiver.try_recv() {
-                        Ok(message) => {
-                            match message {
-                                ServerMessage::Login(name, sender) => {
-                                    self.players.push(Player::new(&name));
-                                    connections.push((name, sender));
-                                },
-                                ServerMessage::Logout(name) => {
-                                    self.logout(&name);
-                                    let r = connections.iter().position(|e| {e.0 == name});
-                                    match r {
-                                        Some(i) => {
-                                            connections.remove(i);
-                                        },
-                                        None => {
-                                            println!("tried to delete connection, but connection wasnt there");
-                                        },
-                                    }
-                                },
-                                ServerMessage::Input { name, mouse, keys, wheel } => {
-                                    self.handle_input(&name, mouse, keys, wheel);
-                                },
-                            }
-                        },
+                        Ok(message) => self.handle_message(message, &mut connections),
                         Err(error) => {
                             match error {
                                 std::sync::mpsc::TryRecvError::Empty => {
                                     break;
                                 },
-                                std::sync::mpsc::TryRecvError::Disconnected => {},
+                                std::sync::mpsc::TryRecvError::Disconnected => {
+                                    // nobody can talk to the game anymore
+                                    self.running = false;
+                                    break;
+                                },
                             }
                         },
                     }
                 }
                 let mut deprecated_connections = vec![];
+                let pack_start = Instant::now();
                 for (i, connection) in connections.iter().enumerate() {
                     let name = &connection.0;
                     let sender = &connection.1;
-                    let r = sender.send(self.pack_objects(name));
+                    let snapshot = self.pack_objects(name);
+                    self.metrics.record_snapshot(snapshot.len());
+                    let r = sender.send(Outgoing::Frame(snapshot));
                     match r {
                         Ok(_) => {},
                         Err(_) => {
@@ -403,27 +659,98 @@ impl Game {
                         },
                     };
                 }
-                // remove in reverse order
+                self.metrics.record_phase("pack_objects", pack_start.elapsed());
+                // remove in reverse order, a connection that went away before its login was accepted never sent a logout
                 for i in deprecated_connections.iter().rev() {
-                    connections.remove(*i);
+                    let (name, _) = connections.remove(*i);
+                    self.logout(&name);
                 }
+                self.metrics.connections = connections.len();
+                // logouts and new accounts reach the file without the tick waiting for the disk
+                self.accounts.write_later();
 
                 thread::sleep(Duration::from_millis(1));
                 if !self.running {
                     break;
                 }
-
-                crate::enemy::handle_effects(&mut self);
-                crate::player::handle_effects(&mut self);
-                crate::item::handle_effects(&mut self);
-                handle_players(&mut self.players, &mut self.collectables);
-                handle_movements(&mut self);
-                handle_collectables(&mut self);
-
-                handle_collision(&mut self);
-                handle_kill_revive(&mut self);
+                if self.paused {
+                    continue;
+                }
+                self.tick_budget += self.speed;
+                while self.tick_budget >= 1.0 {
+                    self.tick_budget -= 1.0;
+                    let start = Instant::now();
+                    self.update();
+                    let elapsed = start.elapsed();
+                    self.metrics.record_tick(elapsed, &self.phases);
+                    tick_time += elapsed;
+                    if self.tick % REPORT_INTERVAL == 0 {
+                        println!("tick {}: {:.3}ms average, {} awake and {} sleeping enemies",
+                            self.tick,
+                            tick_time.as_secs_f64() * 1000.0 / REPORT_INTERVAL as f64,
+                            self.lod.awake,
+                            self.lod.sleeping,
+                        );
+                        tick_time = Duration::ZERO;
+                    }
+                }
             }
-        });
+            self.shutdown(&mut connections);
+        })
+    }
+    fn shutdown(&mut self, connections: &mut Connections) {
+        println!("shutting down...");
+        if let Some(listener) = &self.listener {
+            listener.stop();
+        }
+        // answer whatever is still queued, logins included so they get saved too
+        while let Ok(message) = self.receiver.try_recv() {
+            self.handle_message(message, connections);
+        }
+        for (_, sender) in connections.drain(..) {
+            let _ = sender.send(Outgoing::Close("server is shutting down".to_owned()));
+        }
+        match self.save_players() {
+            Ok(n) => println!("saved {} players", n),
+            Err(e) => println!("saving players failed: {}", e),
+        }
+        if let Err(e) = self.save_accounts() {
+            println!("saving accounts failed: {}", e);
+        }
+    }
+    pub fn update(&mut self) {
+        let mut timer = PhaseTimer::start();
+        crate::lod::handle_lod(self);
+        timer.lap("lod");
+        crate::enemy::handle_effects(self);
+        timer.lap("enemy_effects");
+        crate::player::handle_effects(self);
+        timer.lap("player_effects");
+        crate::item::handle_effects(self);
+        crate::worldobject::handle_world_objects(self);
+        timer.lap("item_effects");
+        handle_players(&mut self.players, &mut self.collectables, &mut self.item_counter);
+        timer.lap("players");
+        handle_movements(self);
+        crate::safezone::handle_safe_zones(self);
+        timer.lap("movements");
+        handle_collectables(self);
+        crate::loot::handle_spawners(self);
+        timer.lap("collectables");
+        handle_discovery(self);
+        crate::waypoint::handle_waypoints(self);
+        crate::trade::handle_trades(self);
+        crate::stats::handle_stats(self);
+        timer.lap("progression");
+
+        handle_enemy_collisions(self);
+        timer.lap("enemy_collisions");
+        handle_collision(self);
+        timer.lap("collision");
+        handle_kill_revive(self);
+        timer.lap("kill_revive");
+        self.phases = timer.finish();
+        self.tick += 1;
     }
     pub fn pack_objects(&mut self, name: &String) -> String {
         let player = match self.get_mut(name) {
@@ -465,6 +792,14 @@ impl Game {
             let acc = draw_object(object, &camera, zoom);
             objects.push_str(&acc);
         }
+        // world objects, everyone sees them
+        for object in self.world_objects.iter() {
+            if vector::distance(camera, (object.x, object.y)).2 > view + object.radius {continue;}
+            for dp in object.draw_packs() {
+                let acc = draw(0.0, &(object.x, object.y), &dp, &camera, zoom);
+                objects.push_str(&acc);
+            }
+        }
         // item effects
         for player in self.players.iter() {
             for item in player.inventory.items.iter() {
@@ -498,16 +833,24 @@ impl Game {
                 objects.push_str(&acc);
             }
 
-            // player effects
-            for effect in player.effects.iter() {
-                match effect {
-                    crate::player::PlayerEffect::Harden { ease, cooldown, speed } => {
-                        let pos = (player.get_x() - 20.0, player.get_y() - 60.0);
-                        let dp = DrawPack::new("rgba(0,0,255,0.5)", Shape::Text { content: format!("cd: {}", *cooldown), size: 20.0 }, (0.0, 0.0));
-                        let acc = draw(0.0, &pos, &dp, &camera, zoom);
-                        objects.push_str(&acc);
-                    },
-                    _ => {}
+            // downed countdown and revive progress, seen by the reviver too
+            if !player.alive {
+                if self.revive.downed_ticks > 0 {
+                    let pos = (player.get_x() - 20.0, player.get_y() + player.get_radius() + 25.0);
+                    let dp = DrawPack::new("rgb(255,80,80)", Shape::Text { content: format!("{}", player.downed), size: 20.0 }, (0.0, 0.0));
+                    let acc = draw(0.0, &pos, &dp, &camera, zoom);
+                    objects.push_str(&acc);
+                }
+                if player.revive_progress > 0 {
+                    let width = 80.0;
+                    let progress = player.revive_progress as Float / self.revive.revive_ticks.max(1) as Float;
+                    let pos = (player.get_x() - width / 2.0, player.get_y() - player.get_radius() - 70.0);
+                    let dp = DrawPack::new("rgba(0,0,0,0.5)", Shape::Rectangle { width, height: 10.0 }, (0.0, 0.0));
+                    let acc = draw(0.0, &pos, &dp, &camera, zoom);
+                    objects.push_str(&acc);
+                    let dp = DrawPack::new("rgb(80,255,80)", Shape::Rectangle { width: width * progress, height: 10.0 }, (0.0, 0.0));
+                    let acc = draw(0.0, &pos, &dp, &camera, zoom);
+                    objects.push_str(&acc);
                 }
             }
         }
@@ -526,22 +869,41 @@ impl Game {
                 let acc = draw(0.0, &(object.get_x(), object.get_y()), &drawpack, &camera, 1.0);
                 objects.push_str(&acc);
 
-                let drawpack = DrawPack::new("white", Shape::Text { content: "Inventory".to_owned(), size: 30.0 }, (-850.0, -350.0));
+                let inventory = &object.inventory;
+                let filter = match inventory.filter {
+                    Filter::All => String::new(),
+                    Filter::Slot(slot) => format!(" [{:?}]", slot).to_lowercase(),
+                    Filter::Other => " [other]".to_owned(),
+                };
+                let title = format!("Inventory {}/{}{}", inventory.items.len(), inventory.capacity, filter);
+                let drawpack = DrawPack::new("white", Shape::Text { content: title, size: 30.0 }, (-850.0, -350.0));
                 let acc = draw(0.0, &(object.get_x(), object.get_y()), &drawpack, &camera, 1.0);
                 objects.push_str(&acc);
 
                 // key bindings
-                for (i, (key, item)) in object.inventory.bindings.iter().enumerate() {
+                for (i, (key, id)) in inventory.bindings.iter().enumerate() {
+                    let item = inventory.index_of(*id).map_or("", |i| inventory.items[i].name.as_str());
                     let drawpack = DrawPack::new("white", Shape::Text { content: format!("{} - {}", key, item), size: 15.0 }, (800.0, -400.0 + 20.0 * i as Float));
                     let acc = draw(0.0, &(object.get_x(), object.get_y()), &drawpack, &camera, 1.0);
                     objects.push_str(&acc);
                 }
 
-                // inventory items
-                for (i, item) in object.inventory.items.iter().enumerate() {
+                // inventory items, scrolled so the selection is visible
+                let visible = inventory.visible();
+                if inventory.scroll > 0 {
+                    let drawpack = DrawPack::new("white", Shape::Text { content: format!("{} more", inventory.scroll), size: 15.0 }, (-650.0, -350.0));
+                    objects.push_str(&draw(0.0, &(object.get_x(), object.get_y()), &drawpack, &camera, 1.0));
+                }
+                let below = visible.len().saturating_sub(inventory.scroll + VISIBLE_ROWS);
+                if below > 0 {
+                    let drawpack = DrawPack::new("white", Shape::Text { content: format!("{} more", below), size: 15.0 }, (-850.0, 390.0));
+                    objects.push_str(&draw(0.0, &(object.get_x(), object.get_y()), &drawpack, &camera, 1.0));
+                }
+                for (row, i) in visible.iter().skip(inventory.scroll).take(VISIBLE_ROWS).enumerate() {
+                    let (i, item) = (*i, &inventory.items[*i]);
                     let line_height = 50.0;
-                    let line_offset = line_height * (i as Float);
-                    match object.inventory.selected_item {
+                    let line_offset = line_height * (row as Float);
+                    match inventory.selected_item {
                         Some(s) => {
                             if i == s {
                                 let drawpack = DrawPack::new("rgba(255,255,255,0.3)", Shape::Rectangle { width: 300.0, height: 40.0 }, (-850.0, -330.0 + line_offset));
@@ -557,16 +919,7 @@ impl Game {
                     else {
                         "rgb(100,0,0)"
                     };
-                    let mut append = "".to_owned();
-                    for effect in item.effects.iter() {
-                        match effect {
-                            crate::item::ItemEffect::Consumable { uses } => {
-                                append.push_str(format!("({})", *uses).as_str());
-                            },
-                            _ => {}
-                        };
-                    }
-                    let drawpack = DrawPack::new(color, Shape::Text { content: format!("{} {}", item.name.clone(), append), size: 30.0 }, (-850.0, -300.0 + line_offset));
+                    let drawpack = DrawPack::new(color, Shape::Text { content: format!("{}{} {}", item.name, item.amount(), item.status()), size: 30.0 }, (-850.0, -300.0 + line_offset));
                     let acc = draw(0.0, &(object.get_x(), object.get_y()), &drawpack, &camera, 1.0);
                     objects.push_str(&acc);
                     match &item.icon {
@@ -580,16 +933,204 @@ impl Game {
             }
         }
 
+        // tooltip of the selected item next to the inventory
+        if let Some(item) = self.players.iter().find(|p| p.name == *name && p.inventory.open)
+            .and_then(|p| p.inventory.selected_item.and_then(|s| p.inventory.items.get(s))) {
+            // about as many characters as fit into the box
+            let lines: Vec<String> = item.description().iter().flat_map(|l| l.wrap(52)).collect();
+            let drawpack = DrawPack::new("rgba(40,40,40,0.85)", Shape::Rectangle { width: 420.0, height: 60.0 + 22.0 * lines.len() as Float }, (-480.0, -400.0));
+            objects.push_str(&draw(0.0, &camera, &drawpack, &camera, 1.0));
+            let drawpack = DrawPack::new("white", Shape::Text { content: format!("{}{}", item.name, item.amount()), size: 22.0 }, (-465.0, -370.0));
+            objects.push_str(&draw(0.0, &camera, &drawpack, &camera, 1.0));
+            for (i, line) in lines.into_iter().enumerate() {
+                let drawpack = DrawPack::new("rgb(210,210,210)", Shape::Text { content: line, size: 14.0 }, (-465.0, -345.0 + 22.0 * i as Float));
+                objects.push_str(&draw(0.0, &camera, &drawpack, &camera, 1.0));
+            }
+        }
+
+        // hotbar with the first items, their keys, cooldowns and charges
+        if let Some(player) = self.get(name) {
+            let slots = player.inventory.items.len().min(HOTBAR_SLOTS);
+            let left = -(slots as Float) * 70.0 / 2.0;
+            for (i, item) in player.inventory.items.iter().take(slots).enumerate() {
+                let slot = (camera.0 + left + 70.0 * i as Float, camera.1 + 380.0);
+                let color = if item.active {
+                    "rgba(0,150,0,0.7)"
+                }
+                else if item.ready() {
+                    "rgba(0,0,0,0.5)"
+                }
+                else {
+                    "rgba(100,0,0,0.6)"
+                };
+                let drawpack = DrawPack::new(color, Shape::Rectangle { width: 60.0, height: 60.0 }, (0.0, 0.0));
+                objects.push_str(&draw(0.0, &slot, &drawpack, &camera, 1.0));
+                match &item.icon {
+                    Some(icon) => objects.push_str(&draw(0.0, &(slot.0 + 8.0, slot.1 + 8.0), icon, &camera, 1.0)),
+                    None => {
+                        let short: String = item.name.chars().take(6).collect();
+                        let drawpack = DrawPack::new("white", Shape::Text { content: short, size: 14.0 }, (4.0, 35.0));
+                        objects.push_str(&draw(0.0, &slot, &drawpack, &camera, 1.0));
+                    },
+                }
+                // the cooldown shrinks from the top
+                let waiting = 1.0 - item.cooldown_progress();
+                if waiting > 0.0 {
+                    let drawpack = DrawPack::new("rgba(0,0,0,0.6)", Shape::Rectangle { width: 60.0, height: 60.0 * waiting }, (0.0, 0.0));
+                    objects.push_str(&draw(0.0, &slot, &drawpack, &camera, 1.0));
+                }
+                let key = player.inventory.bindings.iter().find(|(_, id)| **id == item.id).map(|(k, _)| k.as_str())
+                    .or_else(|| player.controls.key_of(InputAction::UseItem(i)));
+                if let Some(key) = key {
+                    let key = key.trim_start_matches("Key").trim_start_matches("Digit");
+                    let drawpack = DrawPack::new("rgb(255,220,100)", Shape::Text { content: key.to_owned(), size: 12.0 }, (3.0, 12.0));
+                    objects.push_str(&draw(0.0, &slot, &drawpack, &camera, 1.0));
+                }
+                if item.count > 1 {
+                    let drawpack = DrawPack::new("white", Shape::Text { content: item.count.to_string(), size: 14.0 }, (40.0, 55.0));
+                    objects.push_str(&draw(0.0, &slot, &drawpack, &camera, 1.0));
+                }
+                let drawpack = DrawPack::new("white", Shape::Text { content: item.status(), size: 12.0 }, (0.0, 75.0));
+                objects.push_str(&draw(0.0, &slot, &drawpack, &camera, 1.0));
+            }
+            if player.inventory.full > 0 {
+                let drawpack = DrawPack::new("rgb(255,80,80)", Shape::Text { content: "inventory full".to_owned(), size: 20.0 }, (-60.0, 360.0));
+                objects.push_str(&draw(0.0, &camera, &drawpack, &camera, 1.0));
+            }
+        }
+        // trade request or the window with both offers
+        if let (Some(player), Some(trade)) = (self.players.iter().find(|p| p.name == *name), self.trade_of(name)) {
+            let partner = trade.partner(name);
+            let key = |action| player.controls.key_of(action).unwrap_or("?").trim_start_matches("Key").to_owned();
+            if !trade.open {
+                let content = if trade.from == *name {
+                    format!("asked {} for a trade", partner)
+                }
+                else {
+                    format!("{} wants to trade, {} to accept", partner, key(InputAction::RequestTrade))
+                };
+                let drawpack = DrawPack::new("white", Shape::Text { content, size: 20.0 }, (-150.0, -300.0));
+                objects.push_str(&draw(0.0, &camera, &drawpack, &camera, 1.0));
+            }
+            else {
+                let drawpack = DrawPack::new("rgba(50,100,200,0.8)", Shape::Rectangle { width: 400.0, height: 500.0 }, (400.0, -400.0));
+                objects.push_str(&draw(0.0, &camera, &drawpack, &camera, 1.0));
+                let drawpack = DrawPack::new("white", Shape::Text { content: format!("Trade with {}", partner), size: 30.0 }, (420.0, -360.0));
+                objects.push_str(&draw(0.0, &camera, &drawpack, &camera, 1.0));
+                let side = trade.side(name).unwrap();
+                let columns = [("you give", side, Some(player)), ("you get", 1 - side, self.players.iter().find(|p| p.name == partner))];
+                for (column, (title, offer, owner)) in columns.into_iter().enumerate() {
+                    let left = 420.0 + 190.0 * column as Float;
+                    let title = if trade.confirmed[offer] {format!("{} (ok)", title)} else {title.to_owned()};
+                    let drawpack = DrawPack::new("rgb(255,220,100)", Shape::Text { content: title, size: 20.0 }, (left, -320.0));
+                    objects.push_str(&draw(0.0, &camera, &drawpack, &camera, 1.0));
+                    let inventory = match owner {
+                        Some(p) => &p.inventory,
+                        None => continue,
+                    };
+                    for (row, i) in trade.offers[offer].iter().filter_map(|o| inventory.index_of(o.id)).enumerate() {
+                        let item = &inventory.items[i];
+                        let drawpack = DrawPack::new("white", Shape::Text { content: format!("{}{}", item.name, item.amount()), size: 18.0 }, (left, -290.0 + 25.0 * row as Float));
+                        objects.push_str(&draw(0.0, &camera, &drawpack, &camera, 1.0));
+                    }
+                }
+                let hint = format!("{} offer selected, {} confirm, {} cancel", key(InputAction::OfferItem), key(InputAction::ConfirmTrade), key(InputAction::Cancel));
+                let drawpack = DrawPack::new("rgb(200,200,200)", Shape::Text { content: hint, size: 15.0 }, (420.0, 80.0));
+                objects.push_str(&draw(0.0, &camera, &drawpack, &camera, 1.0));
+            }
+        }
+        // teleport menu
+        if let Some(player) = self.players.iter().find(|p| {p.name == *name && p.waypoint_menu_open}) {
+            let first = player.waypoint_page * WAYPOINT_ROWS;
+            let rows = player.waypoints.len().saturating_sub(first).min(WAYPOINT_ROWS);
+            let pages = player.waypoints.len().div_ceil(WAYPOINT_ROWS);
+            let drawpack = DrawPack::new("rgba(20,60,80,0.8)", Shape::Rectangle { width: 500.0, height: 110.0 + 30.0 * rows as Float }, (-250.0, 50.0));
+            let acc = draw(0.0, &camera, &drawpack, &camera, 1.0);
+            objects.push_str(&acc);
+
+            let drawpack = DrawPack::new("white", Shape::Text { content: "Waypoints".to_owned(), size: 30.0 }, (-220.0, 90.0));
+            let acc = draw(0.0, &camera, &drawpack, &camera, 1.0);
+            objects.push_str(&acc);
+
+            let scrolls = player.inventory.count(SCROLL);
+            let cost = if self.teleport_cost == 0 {
+                "free".to_owned()
+            }
+            else {
+                format!("{} scroll(s) each, {} left", self.teleport_cost, scrolls)
+            };
+            let drawpack = DrawPack::new("rgb(180,180,180)", Shape::Text { content: cost, size: 15.0 }, (-220.0, 115.0));
+            let acc = draw(0.0, &camera, &drawpack, &camera, 1.0);
+            objects.push_str(&acc);
+            if pages > 1 {
+                let key = |action| player.controls.key_of(action).unwrap_or("?").to_owned();
+                let content = format!("page {}/{}, {} and {} to turn", player.waypoint_page + 1, pages, key(InputAction::PreviousPage), key(InputAction::NextPage));
+                let drawpack = DrawPack::new("rgb(180,180,180)", Shape::Text { content, size: 15.0 }, (20.0, 90.0));
+                objects.push_str(&draw(0.0, &camera, &drawpack, &camera, 1.0));
+            }
+
+            for (i, waypoint) in player.waypoints.iter().skip(first).take(rows).enumerate() {
+                let color = if player.checkpoint.as_ref() == Some(waypoint) {
+                    "rgb(100,200,255)"
+                }
+                else {
+                    "white"
+                };
+                let drawpack = DrawPack::new(color, Shape::Text { content: format!("{} - {}", i + 1, waypoint), size: 20.0 }, (-220.0, 150.0 + 30.0 * i as Float));
+                let acc = draw(0.0, &camera, &drawpack, &camera, 1.0);
+                objects.push_str(&acc);
+            }
+        }
+        // leaderboard
+        if self.players.iter().any(|p| {p.name == *name && p.leaderboard_open}) {
+            let leaderboard = self.leaderboard();
+            let rows = leaderboard.len().min(crate::stats::LEADERBOARD_ROWS);
+            let drawpack = DrawPack::new("rgba(20,30,80,0.8)", Shape::Rectangle { width: 900.0, height: 80.0 + 30.0 * rows as Float }, (-450.0, -400.0));
+            let acc = draw(0.0, &camera, &drawpack, &camera, 1.0);
+            objects.push_str(&acc);
+
+            let drawpack = DrawPack::new("white", Shape::Text { content: "Leaderboard".to_owned(), size: 30.0 }, (-420.0, -360.0));
+            let acc = draw(0.0, &camera, &drawpack, &camera, 1.0);
+            objects.push_str(&acc);
+
+            for (i, (player, online, stats)) in leaderboard.iter().take(rows).enumerate() {
+                let color = if player == name {
+                    "rgb(255,220,100)"
+                }
+                else if *online {
+                    "white"
+                }
+                else {
+                    "rgb(150,150,150)"
+                };
+                let content = format!("{}. {} - {} ({:.0}) - {} deaths - {} revives - {} items - {:.0} travelled - {} ticks alive",
+                    i + 1,
+                    player,
+                    stats.farthest_area.as_deref().unwrap_or("-"),
+                    stats.farthest,
+                    stats.deaths,
+                    stats.revives_given,
+                    stats.items_collected,
+                    stats.distance,
+                    stats.ticks_alive,
+                );
+                let drawpack = DrawPack::new(color, Shape::Text { content, size: 18.0 }, (-420.0, -320.0 + 30.0 * i as Float));
+                let acc = draw(0.0, &camera, &drawpack, &camera, 1.0);
+                objects.push_str(&acc);
+            }
+        }
+
         objects.push_str("null]}");
         objects
     }
-    pub fn handle_input(&mut self, player_name: &String, mouse: (Float, Float), keys_down: Vec<String>, wheel: i32) {
+    pub fn handle_input(&mut self, player_name: &String, mouse: (Float, Float), keys_down: Vec<String>, wheel: i32, movement: Option<(Float, Float)>) {
         let player = match self.get_mut(player_name) {
             Some(p) => p,
             None => return,
         };
         player.mouse = mouse;
         player.keys_down = keys_down;
+        player.analog = movement.and_then(crate::input::analog);
         if wheel > 0 {
             player.zoom /= 1.1;
         }
@@ -613,11 +1154,26 @@ impl Game {
         let index = self.players.iter().position(|p| {p.name == *player});
         match index {
             Some(i) => {
-                let player = self.players.get_mut(i).unwrap();
-                let mut drop = Collectable::new(player.x, player.y, Color::new(0, 0, 255, 1), vec![]);
-                drop.items.append(&mut player.inventory.items);
-                self.players.remove(i);
-                self.collectables.push(drop);
+                let mut player = self.players.remove(i);
+                self.trades.retain(|t| t.side(player.name.as_str()).is_none());
+                let drop = match self.logout_drop {
+                    LogoutDrop::Everything => true,
+                    LogoutDrop::GuestsOnly => !player.account,
+                    LogoutDrop::Nothing => false,
+                };
+                if drop {
+                    let mut drop = Collectable::dropped(player.x, player.y, vec![]);
+                    drop.items.append(&mut player.inventory.items);
+                    player.inventory.bindings.clear();
+                    self.collectables.push(drop);
+                }
+                if player.account {
+                    self.accounts.store(&player.name, SavedPlayer::from_player(&player));
+                }
+                else if !drop {
+                    // guests keep their items until the same name logs in again
+                    self.saved_players.insert(player.name.clone(), SavedPlayer::from_player(&player));
+                }
             },
             None => { }
         };
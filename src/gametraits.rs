@@ -1,5 +1,5 @@
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{game::{DrawPack, Walls}, wall::WallType, Float};
 
@@ -9,7 +9,7 @@ pub enum EntityIndex {
     Enemy{g: usize, e: usize},
 }
 
-#[derive(Serialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub enum Radius {
     Absolute(Float),
     Relative(Float),
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{vector, Float};
+
+// a client cant grow the binding table without limit
+pub const MAX_BINDINGS: usize = 100;
+// sticks rarely rest at exactly 0
+pub const DEADZONE: Float = 0.15;
+// rounding of the client, a diagonal stick can come out a bit longer than 1
+pub const ANALOG_TOLERANCE: Float = 0.001;
+
+// what a key or gamepad button does, the game only asks for actions
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputAction {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    // moves towards the mouse instead
+    FollowMouse,
+    Slow,
+    ToggleInventory,
+    ToggleLeaderboard,
+    ToggleWaypoints,
+    CollectAll,
+    DropItem,
+    Respawn,
+    UseHeart,
+    // binds the selected item to the next key
+    BindItem,
+    Cancel,
+    SelectNext,
+    SelectPrevious,
+    ActivateItem,
+    DeactivateItem,
+    // by slot and name, while the inventory is open
+    SortInventory,
+    // cycles through showing one slot only, while the inventory is open
+    FilterInventory,
+    // asks the nearest player for a trade or accepts a request
+    RequestTrade,
+    // puts the selected item into the trade or takes it out again
+    OfferItem,
+    ConfirmTrade,
+    // toggles the nth item of the inventory
+    UseItem(usize),
+    // teleports to the nth waypoint of the shown page while the menu is open
+    Teleport(usize),
+    // pages of the waypoint menu, nine waypoints fit on one
+    NextPage,
+    PreviousPage,
+}
+
+// key codes from the browser and gamepad button names like GamepadA to actions,
+// several keys can trigger the same action
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Controls {
+    pub bindings: HashMap<String, InputAction>,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        use InputAction::*;
+        let keys = vec![
+            ("KeyW", MoveUp),
+            ("KeyS", MoveDown),
+            ("KeyA", MoveLeft),
+            ("KeyD", MoveRight),
+            ("Space", FollowMouse),
+            ("ShiftLeft", Slow),
+            ("KeyE", ToggleInventory),
+            ("KeyL", ToggleLeaderboard),
+            ("KeyT", ToggleWaypoints),
+            ("KeyC", CollectAll),
+            ("KeyG", DropItem),
+            ("KeyR", Respawn),
+            ("KeyQ", UseHeart),
+            ("KeyB", BindItem),
+            ("Escape", Cancel),
+            ("ArrowDown", SelectNext),
+            ("ArrowUp", SelectPrevious),
+            ("ArrowRight", ActivateItem),
+            ("ArrowLeft", DeactivateItem),
+            ("KeyO", SortInventory),
+            ("KeyF", FilterInventory),
+            ("KeyY", RequestTrade),
+            ("KeyV", OfferItem),
+            ("Enter", ConfirmTrade),
+            ("PageDown", NextPage),
+            ("PageUp", PreviousPage),
+            // standard gamepad layout, see gamepad_keys in script.js
+            ("GamepadUp", MoveUp),
+            ("GamepadDown", MoveDown),
+            ("GamepadLeft", MoveLeft),
+            ("GamepadRight", MoveRight),
+            ("GamepadLB", Slow),
+            ("GamepadY", ToggleInventory),
+            ("GamepadStart", ToggleLeaderboard),
+            ("GamepadBack", ToggleWaypoints),
+            ("GamepadA", UseHeart),
+            ("GamepadB", Cancel),
+            ("GamepadRB", SelectNext),
+            ("GamepadX", ActivateItem),
+        ];
+        let mut bindings: HashMap<String, InputAction> = keys.into_iter().map(|(key, action)| (key.to_owned(), action)).collect();
+        for d in 1..=9 {
+            bindings.insert(format!("Digit{}", d), Teleport(d - 1));
+        }
+        Self { bindings }
+    }
+}
+
+impl Controls {
+    pub fn bind(&mut self, key: &str, action: InputAction) -> Result<(), String> {
+        if key.is_empty() || key.len() > 32 {
+            return Err(format!("invalid key {:?}", key));
+        }
+        if !self.bindings.contains_key(key) && self.bindings.len() >= MAX_BINDINGS {
+            return Err("too many bindings".to_owned());
+        }
+        self.bindings.insert(key.to_owned(), action);
+        Ok(())
+    }
+    pub fn unbind(&mut self, key: &str) {
+        self.bindings.remove(key);
+    }
+    // a key for hints in the ui, the shortest name when several keys do the same
+    pub fn key_of(&self, action: InputAction) -> Option<&str> {
+        self.bindings.iter().filter(|(_, a)| **a == action).map(|(k, _)| k.as_str()).min_by_key(|k| (k.len(), *k))
+    }
+    // true if one of the keys is bound to the action
+    pub fn any(&self, keys: &[String], action: InputAction) -> bool {
+        keys.iter().any(|key| self.bindings.get(key) == Some(&action))
+    }
+}
+
+// analog movement from the client, None inside the deadzone or for garbage.
+// longer vectors than 1 would be faster than the keys, no stick sends them so they are thrown away
+pub fn analog(movement: (Float, Float)) -> Option<(Float, Float)> {
+    if !movement.0.is_finite() || !movement.1.is_finite() {
+        return None;
+    }
+    let length = vector::abs(movement);
+    if length <= DEADZONE || length > 1.0 + ANALOG_TOLERANCE {
+        return None;
+    }
+    // the strength starts at 0 right outside the deadzone
+    let strength = ((length - DEADZONE) / (1.0 - DEADZONE)).min(1.0);
+    Some(vector::normalize(movement, strength))
+}
+
+#[cfg(test)]
+mod input_tests {
+    use super::*;
+
+    #[test]
+    fn controls_test() {
+        let mut controls = Controls::default();
+        let keys = vec!["KeyW".to_owned(), "GamepadLeft".to_owned()];
+        assert!(controls.any(&keys, InputAction::MoveUp));
+        assert!(controls.any(&keys, InputAction::MoveLeft));
+        assert!(!controls.any(&keys, InputAction::MoveDown));
+
+        controls.bind("KeyW", InputAction::UseItem(2)).unwrap();
+        assert!(controls.any(&keys, InputAction::UseItem(2)));
+        assert!(!controls.any(&keys, InputAction::MoveUp));
+        controls.unbind("KeyW");
+        assert!(!controls.any(&keys, InputAction::UseItem(2)));
+        assert!(controls.bind("", InputAction::MoveUp).is_err());
+
+        // saved as a plain map, the tuple actions as objects
+        let json = serde_json::to_value(&controls).unwrap();
+        assert_eq!(json["Digit1"], serde_json::json!({"Teleport": 0}));
+        assert_eq!(serde_json::from_value::<Controls>(json).unwrap(), controls);
+    }
+
+    #[test]
+    fn analog_test() {
+        assert_eq!(analog((0.1, 0.05)), None);
+        assert_eq!(analog((Float::NAN, 0.5)), None);
+        assert_eq!(analog((1.0, 0.0)), Some((1.0, 0.0)));
+        // cheating clients dont move at all
+        assert_eq!(analog((30.0, 40.0)), None);
+        assert_eq!(analog((1e9, 0.0)), None);
+        assert_eq!(analog((1.01, 0.0)), None);
+        let (x, y) = analog((0.7072, 0.7072)).unwrap();
+        assert!((vector::abs((x, y)) - 1.0).abs() < 1e-9);
+        let (x, _) = analog((0.5, 0.0)).unwrap();
+        assert!(x > 0.0 && x < 0.5);
+    }
+}
@@ -1,13 +1,299 @@
 use std::collections::HashMap;
 
-use crate::item::Item;
+use crate::item::{Activation, Item, Slot};
 
+// items shown at the bottom of the screen
+pub const HOTBAR_SLOTS: usize = 9;
+// stacks a player can carry
+pub const CAPACITY: usize = 20;
+// lines the inventory panel has room for
+pub const VISIBLE_ROWS: usize = 14;
+// ticks the inventory full message stays after a rejected pickup
+pub const FULL_TICKS: usize = 60;
 
-#[derive(Debug, Default)]
+// which items the inventory panel lists
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Filter {
+    #[default]
+    All,
+    Slot(Slot),
+    // items without a slot
+    Other,
+}
+
+impl Filter {
+    pub fn next(self) -> Self {
+        match self {
+            Filter::All => Filter::Slot(Slot::Vision),
+            Filter::Slot(Slot::Vision) => Filter::Slot(Slot::Aura),
+            Filter::Slot(Slot::Aura) => Filter::Slot(Slot::Movement),
+            Filter::Slot(Slot::Movement) => Filter::Slot(Slot::Consumable),
+            Filter::Slot(Slot::Consumable) => Filter::Other,
+            Filter::Other => Filter::All,
+        }
+    }
+    pub fn matches(&self, item: &Item) -> bool {
+        match self {
+            Filter::All => true,
+            Filter::Slot(slot) => item.slot == Some(*slot),
+            Filter::Other => item.slot.is_none(),
+        }
+    }
+}
+
+fn slot_order(item: &Item) -> usize {
+    match item.slot {
+        Some(Slot::Vision) => 0,
+        Some(Slot::Aura) => 1,
+        Some(Slot::Movement) => 2,
+        Some(Slot::Consumable) => 3,
+        None => 4,
+    }
+}
+
+#[derive(Debug)]
 pub struct Inventory {
     pub open: bool,
     pub items: Vec<Item>,
     pub selected_item: Option<usize>,
     pub bind_mode: bool,
+    // key to the id of the bound item, ids survive sorting and removing other items
     pub bindings: HashMap<String, usize>,
+    // stacks, not items
+    pub capacity: usize,
+    pub filter: Filter,
+    // first visible line of the panel
+    pub scroll: usize,
+    // counts down after a pickup didnt fit
+    pub full: usize,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self {
+            open: false,
+            items: vec![],
+            selected_item: None,
+            bind_mode: false,
+            bindings: HashMap::new(),
+            capacity: CAPACITY,
+            filter: Filter::All,
+            scroll: 0,
+            full: 0,
+        }
+    }
+}
+
+impl Inventory {
+    // removes the whole stack at index and keeps the selection pointing at the same item
+    pub fn remove(&mut self, index: usize) -> Item {
+        let item = self.items.remove(index);
+        self.bindings.retain(|_, id| {*id != item.id});
+        match self.selected_item {
+            Some(_) if self.items.is_empty() => self.selected_item = None,
+            Some(s) if s > index || s == self.items.len() => self.selected_item = Some(s - 1),
+            _ => {},
+        }
+        item
+    }
+    // one item off the stack at index, a split item gets a new id so bindings and offers stay with the stack
+    pub fn take_one(&mut self, index: usize, item_counter: &mut usize) -> Option<Item> {
+        let stack = self.items.get_mut(index)?;
+        if stack.count <= 1 {
+            return Some(self.remove(index));
+        }
+        stack.count -= 1;
+        let mut item = stack.clone();
+        item.count = 1;
+        item.id = *item_counter;
+        *item_counter += 1;
+        Some(item)
+    }
+    // puts the item on a stack of the same type or into a free place, gives it back if the inventory is full
+    pub fn add(&mut self, item: Item) -> Result<(), Item> {
+        if let Some(stack) = self.items.iter_mut().find(|s| s.kind == item.kind && s.count + item.count <= s.stack) {
+            stack.count += item.count;
+            return Ok(());
+        }
+        if self.items.len() >= self.capacity {
+            self.full = FULL_TICKS;
+            return Err(item);
+        }
+        self.items.push(item);
+        Ok(())
+    }
+    pub fn count(&self, kind: &str) -> usize {
+        self.items.iter().filter(|i| i.kind == kind).map(|i| i.count).sum()
+    }
+    // takes amount items of a type from the last stacks, nothing if there arent enough
+    pub fn take(&mut self, kind: &str, amount: usize) -> bool {
+        if self.count(kind) < amount {
+            return false;
+        }
+        let mut left = amount;
+        while left > 0 {
+            let index = self.items.iter().rposition(|i| i.kind == kind).unwrap();
+            let taken = left.min(self.items[index].count);
+            self.items[index].count -= taken;
+            left -= taken;
+            if self.items[index].count == 0 {
+                self.remove(index);
+            }
+        }
+        true
+    }
+    pub fn index_of(&self, id: usize) -> Option<usize> {
+        self.items.iter().position(|i| i.id == id)
+    }
+    // indices of the items the filter lets through
+    pub fn visible(&self) -> Vec<usize> {
+        (0..self.items.len()).filter(|i| self.filter.matches(&self.items[*i])).collect()
+    }
+    // moves the selection through the visible items, wrapping around at the ends
+    pub fn select(&mut self, step: isize) {
+        let visible = self.visible();
+        if visible.is_empty() {
+            self.selected_item = None;
+            return;
+        }
+        let position = self.selected_item.and_then(|s| visible.iter().position(|i| *i == s));
+        let next = match position {
+            Some(p) => (p as isize + step).rem_euclid(visible.len() as isize) as usize,
+            None => 0,
+        };
+        self.selected_item = Some(visible[next]);
+        self.follow_selection();
+    }
+    // scrolls the panel so the selected item is on it
+    pub fn follow_selection(&mut self) {
+        let visible = self.visible();
+        let position = match self.selected_item.and_then(|s| visible.iter().position(|i| *i == s)) {
+            Some(p) => p,
+            None => {
+                self.scroll = self.scroll.min(visible.len().saturating_sub(VISIBLE_ROWS));
+                return;
+            },
+        };
+        if position < self.scroll {
+            self.scroll = position;
+        }
+        else if position >= self.scroll + VISIBLE_ROWS {
+            self.scroll = position + 1 - VISIBLE_ROWS;
+        }
+    }
+    pub fn cycle_filter(&mut self) {
+        self.filter = self.filter.next();
+        self.scroll = 0;
+        self.selected_item = None;
+        self.select(0);
+    }
+    // by slot, then by name, the selection and the bindings stay on their items
+    pub fn sort(&mut self) {
+        let selected = self.selected_item.map(|s| self.items[s].id);
+        self.items.sort_by(|a, b| slot_order(a).cmp(&slot_order(b)).then_with(|| a.name.cmp(&b.name)));
+        self.selected_item = selected.and_then(|id| self.index_of(id));
+        self.follow_selection();
+    }
+    pub fn use_item(&mut self, index: usize) {
+        if let Some(item) = self.items.get_mut(index) {
+            item.use_item();
+            self.free_slot(index);
+        }
+    }
+    pub fn set_active(&mut self, index: usize, active: bool) {
+        if let Some(item) = self.items.get_mut(index) {
+            item.set_active(active);
+            self.free_slot(index);
+        }
+    }
+    // an item that was just switched on switches off the other item in its slot
+    fn free_slot(&mut self, index: usize) {
+        let item = &self.items[index];
+        let slot = match item.slot {
+            Some(slot) if item.active => slot,
+            _ => return,
+        };
+        for (i, other) in self.items.iter_mut().enumerate() {
+            if i != index && other.slot == Some(slot) && other.activation == Activation::Toggle {
+                other.active = false;
+            }
+        }
+    }
+    // one active item per slot for inventories that were put together some other way, like a save
+    pub fn enforce_slots(&mut self) {
+        let mut taken = vec![];
+        for item in self.items.iter_mut() {
+            let slot = match item.slot {
+                Some(slot) if item.active => slot,
+                _ => continue,
+            };
+            if taken.contains(&slot) {
+                item.active = false;
+            }
+            else {
+                taken.push(slot);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod inventory_tests {
+    use std::sync::mpsc::channel;
+
+    use crate::game::Game;
+
+    use super::*;
+
+    #[test]
+    fn stacking_capacity_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        let mut inventory = Inventory { capacity: 3, ..Default::default() };
+        for _ in 0..12 {
+            inventory.add(game.create_item("teleportation_scroll").unwrap()).unwrap();
+        }
+        // scrolls stack up to ten
+        assert_eq!(inventory.items.len(), 2);
+        assert_eq!(inventory.count("teleportation_scroll"), 12);
+        inventory.add(game.create_item("monocle").unwrap()).unwrap();
+        let rejected = inventory.add(game.create_item("telescope").unwrap());
+        assert_eq!(rejected.unwrap_err().kind, "telescope");
+        assert_eq!(inventory.full, FULL_TICKS);
+
+        assert!(inventory.take("teleportation_scroll", 3));
+        assert_eq!(inventory.count("teleportation_scroll"), 9);
+        assert_eq!(inventory.items.len(), 2);
+        assert!(!inventory.take("teleportation_scroll", 10));
+        let one = inventory.take_one(0, &mut game.item_counter).unwrap();
+        assert_eq!((one.count, inventory.items[0].count), (1, 8));
+        assert_ne!(one.id, inventory.items[0].id);
+    }
+
+    #[test]
+    fn sort_filter_bindings_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        let mut inventory = Inventory::default();
+        for id in ["telescope", "heatwave", "monocle", "heart"] {
+            inventory.add(game.create_item(id).unwrap()).unwrap();
+        }
+        let heart = inventory.items[3].id;
+        inventory.bindings.insert("KeyH".to_owned(), heart);
+        inventory.selected_item = Some(3);
+        inventory.sort();
+        let names: Vec<&str> = inventory.items.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["monocle", "telescope", "heatwave", "heart"]);
+        assert_eq!(inventory.selected_item, Some(3));
+        // removing an item before it keeps the binding on the heart
+        inventory.remove(0);
+        assert_eq!(inventory.index_of(inventory.bindings["KeyH"]), Some(2));
+
+        inventory.cycle_filter();
+        assert_eq!(inventory.filter, Filter::Slot(Slot::Vision));
+        assert_eq!(inventory.visible(), vec![0]);
+        assert_eq!(inventory.selected_item, Some(0));
+        inventory.select(1);
+        assert_eq!(inventory.selected_item, Some(0));
+    }
 }
@@ -1,170 +1,357 @@
-use crate::{action::Action, game::{DrawPack, Game}, gametraits::{Drawable, Radius}, vector, Float};
+use serde::{Deserialize, Serialize};
+
+use crate::{action::Action, game::{DrawPack, Game}, gametraits::{Drawable, Radius}, player::{Player, PlayerEffect}, worldobject::{WorldObject, WorldObjectKind}, vector, Float};
 use crate::gametraits::*;
 
+// item types the code asks for, see data/items.json
+pub const HEART: &str = "heart";
+pub const SCROLL: &str = "teleportation_scroll";
+
+// how the player turns an item on
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Activation {
+    // works the whole time it is carried
+    Passive,
+    // switched on and off
+    #[default]
+    Toggle,
+    // works for one tick per use, then waits for the cooldown
+    Triggered,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Charges {
+    pub max: usize,
+    // ticks until one used charge comes back, 0 never
+    #[serde(default)]
+    pub recharge: usize,
+    // the item is gone once the last charge is used
+    #[serde(default)]
+    pub consumed: bool,
+}
+
+// at most one active item per slot, see Inventory::use_item
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Slot {
+    Vision,
+    Aura,
+    Movement,
+    Consumable,
+}
 
-#[derive(Debug, Default)]
+// what happens when several active items have the same effect
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stacking {
+    // every item works on its own
+    Independent,
+    // only the first one in the inventory works
+    Unique,
+    // only the strongest one works
+    Max,
+    // the factors multiply but never above cap
+    Multiply {cap: Float},
+}
+
+// items can never make a player faster than this together
+pub const MAX_SPEED_BONUS: Float = 3.0;
+
+#[derive(Debug, Default, Clone)]
 pub struct Item {
     pub id: usize,
+    // id of the item type in the catalogue
+    pub kind: String,
     pub name: String,
     pub active: bool,
     pub effects: Vec<ItemEffect>,
     pub drawpacks: Vec<DrawPack>,
     pub icon: Option<DrawPack>,
+    pub activation: Activation,
+    // ticks between two uses of a triggered item
+    pub cooldown: usize,
+    pub cooldown_left: usize,
+    // triggered items with charges need one per use
+    pub charges: Option<Charges>,
+    pub charges_left: usize,
+    pub recharge_progress: usize,
+    pub slot: Option<Slot>,
+    // identical items in this stack and how many of them fit into it
+    pub count: usize,
+    pub stack: usize,
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum ItemEffect {
     Vision((Float,Float)),
     Speed(Float),
     SlowEnemies{power: Float, radius: Radius, duration: usize},
     ShrinkEnemies{power: Float, radius: Radius, duration: usize},
     Revive{radius: Radius},
-    Consumable{uses: usize},
     PushEnemies{power: Float, radius: Radius},
     RotateEnemies{power: Float, radius: Radius},
-    Harden{limit: usize, cooldown: usize, speed: Float},
-    Usable,
+    Harden{limit: usize, speed: Float},
+    // enemies and projectiles run at power in the middle and at normal speed at the edge
+    DilateTime{power: Float, radius: Radius},
+    // places a well at the mouse that pulls enemies for duration ticks
+    GravityWell{power: Float, radius: Float, duration: usize},
+    // drops a zone for lifetime ticks that slows enemies passing through for duration ticks
+    SlowZone{power: Float, radius: Float, duration: usize, lifetime: usize},
+}
+
+impl ItemEffect {
+    pub fn stacking(&self) -> Stacking {
+        match self {
+            ItemEffect::Vision(_) | ItemEffect::Revive { .. } | ItemEffect::Harden { .. } => Stacking::Unique,
+            ItemEffect::Speed(_) => Stacking::Multiply { cap: MAX_SPEED_BONUS },
+            ItemEffect::PushEnemies { .. } | ItemEffect::RotateEnemies { .. } | ItemEffect::DilateTime { .. } => Stacking::Max,
+            // enemies keep one slow or shrink per item, placed objects are separate anyway
+            ItemEffect::SlowEnemies { .. } | ItemEffect::ShrinkEnemies { .. } | ItemEffect::GravityWell { .. } | ItemEffect::SlowZone { .. } => Stacking::Independent,
+        }
+    }
+    // one line for the tooltip, built from the numbers so new items explain themselves
+    pub fn describe(&self) -> String {
+        match self {
+            ItemEffect::Vision((min, max)) if min == max => format!("fixes the zoom at {}", min),
+            ItemEffect::Vision((min, max)) => format!("zoom between {} and {}", min, max),
+            ItemEffect::Speed(s) => format!("moves at {} speed", percent(*s)),
+            ItemEffect::SlowEnemies { power, radius, duration } => format!("enemies within {} move at {} speed for {} ticks", reach(radius), percent(*power), duration),
+            ItemEffect::ShrinkEnemies { power, radius, duration } => format!("enemies within {} shrink to {} size for {} ticks", reach(radius), percent(*power), duration),
+            ItemEffect::Revive { radius } => format!("revives downed players within {}", reach(radius)),
+            ItemEffect::PushEnemies { power, radius } if *power < 0.0 => format!("pulls enemies within {} in by {} per tick", reach(radius), -power),
+            ItemEffect::PushEnemies { power, radius } => format!("pushes enemies within {} away by {} per tick", reach(radius), power),
+            ItemEffect::RotateEnemies { power, radius } => format!("turns enemies within {} around you by {} degrees per tick", reach(radius), power),
+            ItemEffect::Harden { limit, speed } if *speed == 0.0 => format!("invincible but frozen for {} ticks", limit),
+            ItemEffect::Harden { limit, speed } => format!("invincible at {} speed for {} ticks", percent(*speed), limit),
+            ItemEffect::DilateTime { power, radius } => format!("time within {} runs at {} in the middle", reach(radius), percent(*power)),
+            ItemEffect::GravityWell { power, radius, duration } => format!("places a well of {} at the mouse, it pulls enemies by {} per tick for {} ticks", radius, power, duration),
+            ItemEffect::SlowZone { power, radius, duration, lifetime } => format!("drops a zone of {} for {} ticks, enemies in it move at {} speed for {} ticks", radius, lifetime, percent(*power), duration),
+        }
+    }
+    // compares effects of the same type for Stacking::Max
+    fn strength(&self) -> Float {
+        match self {
+            ItemEffect::PushEnemies { power, .. } | ItemEffect::RotateEnemies { power, .. } => power.abs(),
+            ItemEffect::DilateTime { power, .. } => 1.0 - *power,
+            _ => 0.0,
+        }
+    }
+}
+
+// numbers of ItemEffect::describe as players read them
+fn percent(f: Float) -> String {
+    format!("{:.0}%", f * 100.0)
+}
+
+fn reach(radius: &Radius) -> String {
+    match radius {
+        Radius::Absolute(r) => format!("{}", r),
+        Radius::Relative(r) => format!("{} times your size", r),
+    }
+}
+
+// the active effects that work after the stacking rules, with the index of their item
+pub fn resolve_effects(items: &[Item]) -> Vec<(usize, ItemEffect)> {
+    let mut resolved: Vec<(usize, ItemEffect)> = vec![];
+    for (i, item) in items.iter().enumerate() {
+        if !item.active {continue;}
+        // a stack counts as that many items
+        for effect in item.effects.iter().flat_map(|e| std::iter::repeat(e).take(item.count)) {
+            let same = resolved.iter().position(|(_, r)| std::mem::discriminant(r) == std::mem::discriminant(effect));
+            match (effect.stacking(), same) {
+                (Stacking::Multiply { cap }, None) => match effect {
+                    ItemEffect::Speed(f) => resolved.push((i, ItemEffect::Speed(f.min(cap)))),
+                    _ => resolved.push((i, effect.clone())),
+                },
+                (Stacking::Independent, _) | (_, None) => resolved.push((i, effect.clone())),
+                (Stacking::Unique, Some(_)) => {},
+                (Stacking::Max, Some(s)) => {
+                    if effect.strength() > resolved[s].1.strength() {
+                        resolved[s] = (i, effect.clone());
+                    }
+                },
+                (Stacking::Multiply { cap }, Some(s)) => match (&mut resolved[s].1, effect) {
+                    (ItemEffect::Speed(a), ItemEffect::Speed(b)) => *a = (*a * b).min(cap),
+                    _ => {},
+                },
+            }
+        }
+    }
+    resolved
+}
+
+fn world_object(player: &Player, item: &Item, position: (Float, Float), radius: Float, lifetime: usize, kind: WorldObjectKind) -> WorldObject {
+    WorldObject {
+        x: position.0,
+        y: position.1,
+        radius,
+        ticks_left: lifetime,
+        owner: player.name.clone(),
+        origin: item.id,
+        kind,
+    }
 }
 
 pub fn handle_effects(game: &mut Game) {
+    for player in game.players.iter_mut() {
+        for item in player.inventory.items.iter_mut() {
+            item.tick();
+        }
+        player.inventory.enforce_slots();
+    }
     let mut actions: Vec<(usize, Action)> = vec![];
     let mut deletions: Vec<(usize, Action)> = vec![];
     for (p, player) in game.players.iter().enumerate() {
-        for (i, item) in player.inventory.items.iter().enumerate() {
-            if !item.active {continue;}
-            for (e, effect) in item.effects.iter().enumerate() {
-                match effect {
-                    ItemEffect::Vision(zoom) => {
-                        actions.push((p, Action::SetPlayerZoomlimit(*zoom)));
-                    },
-                    ItemEffect::Speed(s) => {
-                        actions.push((p, Action::MulPlayerSpeedMultiplier { f: *s }));
-                    },
-                    ItemEffect::SlowEnemies{power, radius, duration } => {
-                        for group in game.enemies.iter_mut() {
-                            for enemy in group.1.iter_mut() {
-                                if vector::distance((player.get_x(), player.get_y()), (enemy.get_x(), enemy.get_y())).2 - enemy.get_radius() <= radius.translate(player.get_radius()) {
-                                    // check if effect of this item id is already applied
-                                    let effect = enemy.effects.iter_mut().find(|e| {
+        for (i, effect) in resolve_effects(&player.inventory.items) {
+            let item = &player.inventory.items[i];
+            match &effect {
+                ItemEffect::Vision(zoom) => {
+                    actions.push((p, Action::SetPlayerZoomlimit(*zoom)));
+                },
+                ItemEffect::Speed(s) => {
+                    actions.push((p, Action::MulPlayerSpeedMultiplier { f: *s }));
+                },
+                ItemEffect::SlowEnemies{power, radius, duration } => {
+                    for group in game.enemies.iter_mut() {
+                        for enemy in group.1.iter_mut() {
+                            if enemy.sleeping {continue;}
+                            if vector::distance((player.get_x(), player.get_y()), (enemy.get_x(), enemy.get_y())).2 - enemy.get_radius() <= radius.translate(player.get_radius()) {
+                                // check if effect of this item id is already applied
+                                let effect = enemy.effects.iter_mut().find(|e| {
+                                    match e {
+                                        crate::enemy::EnemyEffect::SpeedAlter { origin, power: slow, ease } => {
+                                            *origin == item.id
+                                        },
+                                        _ => {
+                                            false
+                                        }
+                                    }
+                                });
+                                match effect {
+                                    Some(e) => {
                                         match e {
                                             crate::enemy::EnemyEffect::SpeedAlter { origin, power: slow, ease } => {
-                                                *origin == item.id
+                                                *ease = *duration;
                                             },
                                             _ => {
-                                                false
+                                                // do nothing
                                             }
                                         }
-                                    });
-                                    match effect {
-                                        Some(e) => {
-                                            match e {
-                                                crate::enemy::EnemyEffect::SpeedAlter { origin, power: slow, ease } => {
-                                                    *ease = *duration;
-                                                },
-                                                _ => {
-                                                    // do nothing
-                                                }
-                                            }
-                                        },
-                                        None => {
-                                            enemy.effects.push(crate::enemy::EnemyEffect::SpeedAlter { power: *power, ease: *duration, origin: item.id });
-                                        },
-                                    }
+                                    },
+                                    None => {
+                                        enemy.effects.push(crate::enemy::EnemyEffect::SpeedAlter { power: *power, ease: *duration, origin: item.id });
+                                    },
                                 }
                             }
                         }
-                    },
-                    ItemEffect::ShrinkEnemies{power, radius, duration } => {
-                        for group in game.enemies.iter_mut() {
-                            for enemy in group.1.iter_mut() {
-                                if vector::distance((player.get_x(), player.get_y()), (enemy.get_x(), enemy.get_y())).2 - enemy.get_radius() <= radius.translate(player.get_radius()) {
-                                    // check if effect of this item id is already applied
-                                    let effect = enemy.effects.iter_mut().find(|e| {
+                    }
+                },
+                ItemEffect::ShrinkEnemies{power, radius, duration } => {
+                    for group in game.enemies.iter_mut() {
+                        for enemy in group.1.iter_mut() {
+                            if enemy.sleeping {continue;}
+                            if vector::distance((player.get_x(), player.get_y()), (enemy.get_x(), enemy.get_y())).2 - enemy.get_radius() <= radius.translate(player.get_radius()) {
+                                // check if effect of this item id is already applied
+                                let effect = enemy.effects.iter_mut().find(|e| {
+                                    match e {
+                                        crate::enemy::EnemyEffect::Shrink { origin, power, ease, start } => {
+                                            *origin == item.id
+                                        },
+                                        _ => {
+                                            false
+                                        }
+                                    }
+                                });
+                                match effect {
+                                    Some(e) => {
                                         match e {
                                             crate::enemy::EnemyEffect::Shrink { origin, power, ease, start } => {
-                                                *origin == item.id
+                                                *ease = *duration;
                                             },
                                             _ => {
-                                                false
+                                                // do nothing
                                             }
                                         }
-                                    });
-                                    match effect {
-                                        Some(e) => {
-                                            match e {
-                                                crate::enemy::EnemyEffect::Shrink { origin, power, ease, start } => {
-                                                    *ease = *duration;
-                                                },
-                                                _ => {
-                                                    // do nothing
-                                                }
-                                            }
-                                        },
-                                        None => {
-                                            enemy.effects.push(crate::enemy::EnemyEffect::Shrink { power: *power, ease: *duration, origin: item.id, start: *duration });
-                                        },
-                                    }
+                                    },
+                                    None => {
+                                        enemy.effects.push(crate::enemy::EnemyEffect::Shrink { power: *power, ease: *duration, origin: item.id, start: *duration });
+                                    },
                                 }
                             }
                         }
-                    },
-                    ItemEffect::Revive { radius } => {
-                        actions.push((p, Action::RevivePlayers { radius: *radius }));
-                    },
-                    ItemEffect::Consumable { uses } => {
-                        if *uses == 0 {
-                            deletions.push((p, Action::RemovePlayerItem { item: i }));
-                        }
-                        else {
-                            deletions.push((p, Action::DecreaseItemEffect { item: i, effect: e }));
-                        }
-                    },
-                    ItemEffect::PushEnemies { power, radius } => {
-                        for (g, group) in game.enemies.iter().enumerate() {
-                            for (e, enemy) in group.1.iter().enumerate() {
-                                let dist = vector::distance((player.get_x(), player.get_y()), (enemy.get_x(), enemy.get_y()));
-                                if dist.2 <= radius.translate(player.get_radius()) + enemy.get_radius() {
-                                    let add = vector::normalize((dist.0, dist.1), *power);
-                                    actions.push((e, Action::AddEnemyPosition { group: g, x: add.0, y: add.1 }));
-                                }
+                    }
+                },
+                ItemEffect::Revive { radius } => {
+                    actions.push((p, Action::RevivePlayers { radius: *radius }));
+                },
+                ItemEffect::PushEnemies { power, radius } => {
+                    for (g, group) in game.enemies.iter().enumerate() {
+                        for (e, enemy) in group.1.iter().enumerate() {
+                            if enemy.sleeping {continue;}
+                            let dist = vector::distance((player.get_x(), player.get_y()), (enemy.get_x(), enemy.get_y()));
+                            if dist.2 <= radius.translate(player.get_radius()) + enemy.get_radius() {
+                                let add = vector::normalize((dist.0, dist.1), *power);
+                                actions.push((e, Action::AddEnemyPosition { group: g, x: add.0, y: add.1 }));
                             }
                         }
-                    },
-                    ItemEffect::RotateEnemies { power, radius } => {
-                        for (g, group) in game.enemies.iter().enumerate() {
-                            for (e, enemy) in group.1.iter().enumerate() {
-                                let dist = vector::distance((player.get_x(), player.get_y()), (enemy.get_x(), enemy.get_y()));
-                                if dist.2 <= radius.translate(player.get_radius()) + enemy.get_radius() {
-                                    let angle = vector::angle_from_point((dist.0, dist.1));
-                                    let newu = vector::point_from_angle(angle + *power);
-                                    let newp = vector::normalize((newu.0, newu.1), dist.2);
-                                    let new = (player.get_x() + newp.0, player.get_y() + newp.1);
-                                    let add = (new.0 - enemy.get_x(), new.1 - enemy.get_y());
-                                    actions.push((e, Action::AddEnemyPosition { group: g, x: add.0, y: add.1 }));
-                                }
+                    }
+                },
+                ItemEffect::RotateEnemies { power, radius } => {
+                    for (g, group) in game.enemies.iter().enumerate() {
+                        for (e, enemy) in group.1.iter().enumerate() {
+                            if enemy.sleeping {continue;}
+                            let dist = vector::distance((player.get_x(), player.get_y()), (enemy.get_x(), enemy.get_y()));
+                            if dist.2 <= radius.translate(player.get_radius()) + enemy.get_radius() {
+                                let angle = vector::angle_from_point((dist.0, dist.1));
+                                let newu = vector::point_from_angle(angle + *power);
+                                let newp = vector::normalize((newu.0, newu.1), dist.2);
+                                let new = (player.get_x() + newp.0, player.get_y() + newp.1);
+                                let add = (new.0 - enemy.get_x(), new.1 - enemy.get_y());
+                                actions.push((e, Action::AddEnemyPosition { group: g, x: add.0, y: add.1 }));
                             }
                         }
-                    },
-                    ItemEffect::Harden { limit, cooldown, speed } => {
-                        let effect = player.effects.iter().position(|e| {
-                            match e {
-                                crate::player::PlayerEffect::Harden { ease, cooldown, speed } => true,
-                                _ => false,
+                    }
+                },
+                ItemEffect::Harden { limit, speed } => {
+                    let effect = player.effects.iter().position(|e| {
+                        match e {
+                            crate::player::PlayerEffect::Harden { ease, speed } => true,
+                            _ => false,
+                        }
+                    });
+                    match effect {
+                        Some(effect) => {
+                            
+                        },
+                        None => {
+                            actions.push((p, Action::PushPlayerEffect(crate::player::PlayerEffect::Harden { ease: *limit, speed: *speed })));
+                        },
+                    }
+                },
+                ItemEffect::GravityWell { power, radius, duration } => {
+                    // the mouse is sent in screen pixels from the middle of the screen
+                    let position = (player.get_x() + player.mouse.0 / player.zoom, player.get_y() + player.mouse.1 / player.zoom);
+                    let kind = WorldObjectKind::GravityWell { power: *power };
+                    actions.push((p, Action::SpawnWorldObject(world_object(player, item, position, *radius, *duration, kind))));
+                },
+                ItemEffect::SlowZone { power, radius, duration, lifetime } => {
+                    let kind = WorldObjectKind::SlowZone { power: *power, duration: *duration };
+                    actions.push((p, Action::SpawnWorldObject(world_object(player, item, (player.get_x(), player.get_y()), *radius, *lifetime, kind))));
+                },
+                ItemEffect::DilateTime { power, radius } => {
+                    let range = radius.translate(player.get_radius());
+                    for group in game.enemies.iter_mut() {
+                        for enemy in group.1.iter_mut() {
+                            if enemy.sleeping {continue;}
+                            let dist = vector::distance((player.get_x(), player.get_y()), (enemy.get_x(), enemy.get_y())).2 - enemy.get_radius();
+                            if dist > range {continue;}
+                            let f = *power + (1.0 - *power) * (dist.max(0.0) / range);
+                            // overlapping bubbles dont stack, the strongest one counts
+                            if f < enemy.time_scale {
+                                enemy.speed_multiplier *= f / enemy.time_scale;
+                                enemy.time_scale = f;
                             }
-                        });
-                        match effect {
-                            Some(effect) => {
-                                
-                            },
-                            None => {
-                                actions.push((p, Action::PushPlayerEffect(crate::player::PlayerEffect::Harden { ease: *limit, cooldown: *cooldown, speed: *speed })));
-                            },
                         }
-                    },
-                    ItemEffect::Usable => {
-                        actions.push((p, Action::SetItemActive { i, v: false }));
-                    },
-                }
+                    }
+                },
             }
         }
     }
@@ -174,19 +361,292 @@ pub fn handle_effects(game: &mut Game) {
     for (entity, action) in deletions.iter().rev() {
         action.execute(game, *entity);
     }
+    // a use of a triggered item only lasts one tick, consumed items go once they are empty
+    for player in game.players.iter_mut() {
+        for item in player.inventory.items.iter_mut() {
+            if item.activation == Activation::Triggered {
+                item.active = false;
+            }
+        }
+        while let Some(i) = player.inventory.items.iter().position(|item| item.used_up()) {
+            player.inventory.remove(i);
+        }
+    }
 }
 
 impl Item {
-    pub fn new(name: &str, effects: Vec<ItemEffect>, drawpacks: Vec<DrawPack>, item_counter: &mut usize, icon: Option<DrawPack>) -> Self {
+    pub fn new(kind: &str, name: &str, effects: Vec<ItemEffect>, drawpacks: Vec<DrawPack>, item_counter: &mut usize, icon: Option<DrawPack>) -> Self {
         let item = Item {
+            kind: kind.to_owned(),
             name: name.to_owned(),
             active: false,
             effects,
             drawpacks,
             id: *item_counter,
             icon,
+            count: 1,
+            stack: 1,
+            ..Default::default()
         };
         *item_counter += 1;
         item
     }
+    // tooltip lines, what the effects do and how the item is used
+    pub fn description(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.effects.iter().map(|e| e.describe()).collect();
+        if lines.is_empty() {
+            lines.push("no effect of its own".to_owned());
+        }
+        match self.activation {
+            Activation::Passive => lines.push("always works while carried".to_owned()),
+            Activation::Toggle => lines.push("switched on and off".to_owned()),
+            Activation::Triggered if self.cooldown > 0 => lines.push(format!("used with its key, {} ticks cooldown", self.cooldown)),
+            Activation::Triggered => lines.push("used with its key".to_owned()),
+        }
+        if let Some(charges) = self.charges {
+            let mut line = format!("{} charges", charges.max);
+            if charges.recharge > 0 {
+                line.push_str(&format!(", one comes back every {} ticks", charges.recharge));
+            }
+            if charges.consumed {
+                line.push_str(", gone after the last one");
+            }
+            lines.push(line);
+        }
+        if let Some(slot) = self.slot {
+            lines.push(format!("{:?} slot, one active at a time", slot).to_lowercase());
+        }
+        if self.stack > 1 {
+            lines.push(format!("stacks up to {}", self.stack));
+        }
+        lines
+    }
+    // shown after the name of stacks
+    pub fn amount(&self) -> String {
+        if self.count > 1 {format!(" x{}", self.count)} else {String::new()}
+    }
+    // what pressing the key of the item does
+    pub fn use_item(&mut self) {
+        match self.activation {
+            Activation::Passive => {},
+            Activation::Toggle => self.active = !self.active,
+            Activation::Triggered => self.trigger(),
+        }
+    }
+    pub fn set_active(&mut self, active: bool) {
+        match self.activation {
+            Activation::Passive => {},
+            Activation::Toggle => self.active = active,
+            Activation::Triggered => if active {self.trigger()},
+        }
+    }
+    pub fn ready(&self) -> bool {
+        self.cooldown_left == 0 && (self.charges.is_none() || self.charges_left > 0)
+    }
+    fn trigger(&mut self) {
+        if self.active || !self.ready() {return;}
+        self.active = true;
+        self.cooldown_left = self.cooldown;
+        if self.charges.is_some() {
+            self.charges_left -= 1;
+        }
+    }
+    // counts down the cooldown and brings back charges, once per tick
+    pub fn tick(&mut self) {
+        if self.activation == Activation::Passive {
+            self.active = true;
+        }
+        self.cooldown_left = self.cooldown_left.saturating_sub(1);
+        match self.charges {
+            Some(charges) if charges.recharge > 0 && self.charges_left < charges.max => {
+                self.recharge_progress += 1;
+                if self.recharge_progress >= charges.recharge {
+                    self.recharge_progress = 0;
+                    self.charges_left += 1;
+                }
+            },
+            _ => self.recharge_progress = 0,
+        }
+    }
+    fn used_up(&self) -> bool {
+        !self.active && self.charges_left == 0 && self.charges.map_or(false, |c| c.consumed)
+    }
+    // how far the cooldown is, 1 is ready
+    pub fn cooldown_progress(&self) -> Float {
+        if self.cooldown == 0 {
+            return 1.0;
+        }
+        1.0 - self.cooldown_left as Float / self.cooldown as Float
+    }
+    // shown behind the name in the inventory and under the hotbar slot
+    pub fn status(&self) -> String {
+        let mut status = vec![];
+        if let Some(charges) = self.charges {
+            status.push(format!("{}/{}", self.charges_left, charges.max));
+        }
+        if self.cooldown_left > 0 {
+            status.push(format!("cd {}", self.cooldown_left));
+        }
+        match self.activation {
+            Activation::Passive => status.push("passive".to_owned()),
+            _ => {},
+        }
+        if let Some(slot) = self.slot {
+            status.push(format!("[{:?}]", slot).to_lowercase());
+        }
+        status.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod item_tests {
+    use std::sync::mpsc::channel;
+
+    use crate::{enemy::{Enemy, EnemyEffect}, player::Player};
+
+    use super::*;
+
+    fn time_left(game: &Game) -> usize {
+        match &game.enemies[0].1[0].effects[0] {
+            EnemyEffect::Shoot { time_left, .. } => *time_left,
+            _ => panic!("no shoot effect"),
+        }
+    }
+
+    #[test]
+    fn hourglass_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.enemies.clear();
+        let mut turret = Enemy::new(50.0, 0.0, (0.0, 0.0), 10.0, "black");
+        turret.effects.push(EnemyEffect::Shoot { lifetime: 10, radius: Radius::Absolute(1.0), projectile_radius: 5.0, speed: 1.0, time_left: 100, cooldown: 100, color: "black".to_owned(), effects: vec![], under_dps: vec![] });
+        game.enemies.push((vec![], vec![turret]));
+        let mut player = Player::new(&"jo".to_owned());
+        player.radius_multiplier = 1.0;
+        let mut hourglass = game.create_item("hourglass").unwrap();
+        hourglass.active = true;
+        player.inventory.items.push(hourglass);
+        game.players.push(player);
+
+        for _ in 0..10 {
+            crate::enemy::handle_effects(&mut game);
+            handle_effects(&mut game);
+        }
+        let turret = &game.enemies[0].1[0];
+        assert!(turret.speed_multiplier < 1.0);
+        // the cooldown runs slower inside the bubble
+        assert!(time_left(&game) > 90);
+
+        // outside of it time runs normally again
+        game.enemies[0].1[0].x = 5000.0;
+        let before = time_left(&game);
+        for _ in 0..11 {
+            crate::enemy::handle_effects(&mut game);
+            handle_effects(&mut game);
+        }
+        assert_eq!(game.enemies[0].1[0].speed_multiplier, 1.0);
+        assert!(before - time_left(&game) >= 10);
+    }
+
+    #[test]
+    fn activation_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        let mut player = Player::new(&"jo".to_owned());
+        for id in ["dragonfire_rune", "heart", "puddle"] {
+            player.inventory.items.push(game.create_item(id).unwrap());
+        }
+        game.players.push(player);
+        handle_effects(&mut game);
+        let items = &mut game.players[0].inventory.items;
+        // passive items cant be switched off
+        assert!(items[0].active);
+        items[0].use_item();
+        assert!(items[0].active);
+
+        // one charge per use, the cooldown blocks the second one
+        items[2].use_item();
+        assert_eq!(items[2].charges_left, 1);
+        handle_effects(&mut game);
+        let items = &mut game.players[0].inventory.items;
+        assert!(!items[2].active);
+        items[2].use_item();
+        assert_eq!(items[2].charges_left, 1);
+        items[2].cooldown_left = 0;
+        items[2].use_item();
+        assert_eq!(items[2].charges_left, 0);
+        // and comes back over time
+        for _ in 0..1800 {
+            handle_effects(&mut game);
+        }
+        assert_eq!(game.players[0].inventory.items[2].charges_left, 1);
+
+        // the heart is gone with its last charge
+        game.players[0].inventory.items[1].charges_left = 1;
+        game.players[0].inventory.items[1].use_item();
+        handle_effects(&mut game);
+        assert!(game.players[0].inventory.items.iter().all(|i| i.kind != HEART));
+    }
+
+    #[test]
+    fn slot_stacking_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        let mut player = Player::new(&"jo".to_owned());
+        for id in ["monocle", "telescope", "windaura", "orbit"] {
+            player.inventory.items.push(game.create_item(id).unwrap());
+        }
+        for _ in 0..10 {
+            player.inventory.items.push(game.create_item("dragonfire_rune").unwrap());
+        }
+        // switching on the telescope takes the vision slot from the monocle
+        player.inventory.use_item(0);
+        player.inventory.use_item(1);
+        assert!(!player.inventory.items[0].active);
+        assert!(player.inventory.items[1].active);
+        // two auras from a save, the first one stays
+        player.inventory.items[2].active = true;
+        player.inventory.items[3].active = true;
+        player.inventory.enforce_slots();
+        assert!(!player.inventory.items[3].active);
+        player.inventory.items[0].active = true;
+        for item in player.inventory.items.iter_mut().skip(4) {
+            item.tick();
+        }
+
+        let resolved = resolve_effects(&player.inventory.items);
+        // one zoom limit, the first item wins
+        let visions: Vec<&(usize, ItemEffect)> = resolved.iter().filter(|(_, e)| matches!(e, ItemEffect::Vision(_))).collect();
+        assert_eq!(visions.len(), 1);
+        assert_eq!(visions[0].0, 0);
+        // ten runes are one capped speed effect
+        let speeds: Vec<Float> = resolved.iter().filter_map(|(_, e)| match e {
+            ItemEffect::Speed(s) => Some(*s),
+            _ => None,
+        }).collect();
+        assert_eq!(speeds.len(), 1);
+        assert!((speeds[0] - 1.1f64.powi(10)).abs() < 1e-9);
+        player.inventory.items.push(game.create_item("speedup").unwrap());
+        player.inventory.use_item(14);
+        let resolved = resolve_effects(&player.inventory.items);
+        assert!(resolved.iter().any(|(_, e)| matches!(e, ItemEffect::Speed(s) if *s == MAX_SPEED_BONUS)));
+    }
+
+    #[test]
+    fn description_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        let sandstorm = game.create_item("sandstorm").unwrap();
+        assert_eq!(sandstorm.description()[0], "enemies within 7 times your size shrink to 50% size for 100 ticks");
+        let puddle = game.create_item("puddle").unwrap();
+        let lines = puddle.description();
+        assert!(lines.contains(&"used with its key, 120 ticks cooldown".to_owned()));
+        assert!(lines.contains(&"2 charges, one comes back every 1800 ticks".to_owned()));
+        // every item of the catalogue explains itself
+        let ids: Vec<String> = game.catalogue.items.iter().map(|i| i.id.clone()).collect();
+        for id in ids {
+            let item = game.create_item(&id).unwrap();
+            assert!(item.description().len() > item.effects.len());
+        }
+    }
 }
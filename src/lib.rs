@@ -0,0 +1,42 @@
+#![allow(warnings)]
+
+pub mod accounts;
+pub mod action;
+pub mod admin;
+pub mod bits;
+pub mod broadphase;
+pub mod catalogue;
+pub mod collectable;
+pub mod color;
+pub mod console;
+pub mod enemy;
+pub mod game;
+pub mod gametraits;
+pub mod http;
+pub mod input;
+pub mod inventory;
+pub mod item;
+pub mod lod;
+pub mod loot;
+pub mod math;
+pub mod metrics;
+pub mod parallel;
+pub mod parser;
+pub mod persistence;
+pub mod player;
+pub mod profiler;
+pub mod revive;
+pub mod safezone;
+pub mod server;
+pub mod shutdown;
+pub mod spawner;
+pub mod stats;
+pub mod string;
+pub mod trade;
+pub mod vector;
+pub mod wall;
+pub mod waypoint;
+pub mod websocket;
+pub mod worldobject;
+
+pub type Float = f64;
@@ -0,0 +1,116 @@
+use crate::{game::Game, gametraits::*, vector, Float};
+
+// level of detail for enemies nobody is close to
+// sleeping enemies only move and collide every interval ticks and then move interval times as far
+// their lifetimes, cooldowns and eases still count every tick
+pub struct Lod {
+    pub enabled: bool,
+    // distance to the edge of a players view after which enemies fall asleep
+    pub radius: Float,
+    pub interval: usize,
+    // counters of the last tick
+    pub awake: usize,
+    pub sleeping: usize,
+    // sleeping enemies that didnt move or collide, the last tick and since the start
+    pub skipped: usize,
+    pub skipped_total: usize,
+}
+
+impl Default for Lod {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            radius: 2000.0,
+            interval: 8,
+            awake: 0,
+            sleeping: 0,
+            skipped: 0,
+            skipped_total: 0,
+        }
+    }
+}
+
+pub fn handle_lod(game: &mut Game) {
+    let tick = game.tick;
+    let lod = &mut game.lod;
+    lod.awake = 0;
+    lod.sleeping = 0;
+    lod.skipped = 0;
+    // an interval of 0 would freeze sleeping enemies
+    let interval = lod.interval.max(1);
+    // the same area a player can see in pack_objects plus the lod radius
+    let players: Vec<((Float, Float), Float)> = game.players.iter().map(|p| {
+        ((p.get_x(), p.get_y()), 1000.0 / p.zoom + lod.radius)
+    }).collect();
+    for (g, group) in game.enemies.iter_mut().enumerate() {
+        // groups wake up on different ticks so the work is spread evenly
+        let step_tick = (tick + g) % interval == 0;
+        for enemy in group.1.iter_mut() {
+            let reach = enemy.view_radius.translate(enemy.get_radius());
+            let near = !lod.enabled || players.iter().any(|(position, radius)| {
+                vector::distance(*position, (enemy.get_x(), enemy.get_y())).2 - reach <= *radius
+            });
+            enemy.sleeping = !near;
+            if near {
+                enemy.lod_skip = false;
+                enemy.lod_step = 1.0;
+                lod.awake += 1;
+            }
+            else {
+                enemy.lod_skip = !step_tick;
+                enemy.lod_step = interval as Float;
+                lod.sleeping += 1;
+                if enemy.lod_skip {
+                    lod.skipped += 1;
+                }
+            }
+        }
+    }
+    lod.skipped_total += lod.skipped;
+}
+
+#[cfg(test)]
+mod lod_tests {
+    use std::sync::mpsc::channel;
+
+    use crate::enemy::{self, Enemy, EnemyEffect};
+
+    use super::*;
+
+    #[test]
+    fn sleeping_lifetime_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.enemies = vec![(vec![], vec![])];
+        let mut enemy = Enemy::new(0.0, 0.0, (0.0, 0.0), 10.0, "red");
+        enemy.effects.push(EnemyEffect::Lifetime(20));
+        game.enemies[0].1.push(enemy);
+        // nobody is close so the enemy sleeps, it still has to die after its lifetime
+        for tick in 0..20 {
+            game.tick = tick;
+            handle_lod(&mut game);
+            assert!(game.enemies[0].1[0].sleeping);
+            enemy::handle_effects(&mut game);
+        }
+        assert_eq!(game.enemies[0].1.len(), 1);
+        game.tick = 20;
+        handle_lod(&mut game);
+        enemy::handle_effects(&mut game);
+        assert!(game.enemies[0].1.is_empty());
+        // ticks 0, 8 and 16 of the 21 were step ticks
+        assert_eq!(game.lod.skipped_total, 18);
+    }
+
+    #[test]
+    fn zero_interval_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.enemies = vec![(vec![], vec![Enemy::new(0.0, 0.0, (1.0, 0.0), 10.0, "red")])];
+        game.lod.interval = 0;
+        handle_lod(&mut game);
+        // without lod every tick is a step tick of one tick
+        let enemy = &game.enemies[0].1[0];
+        assert!(enemy.sleeping && !enemy.lod_skip);
+        assert_eq!(enemy.lod_step, 1.0);
+    }
+}
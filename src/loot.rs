@@ -0,0 +1,293 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{catalogue::{self, ItemCatalogue}, collectable::Collectable, color::Color, game::Game, vector::random_point, Float};
+
+// compiled in for Game::new and the tests, the server reads LOOT_PATH of main.rs instead
+const BUILTIN: &str = include_str!("../data/loot.json");
+
+// how hard an item is to find, collectables show it with their color
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Legendary,
+}
+
+impl Rarity {
+    pub fn color(&self) -> Color {
+        match self {
+            Rarity::Common => Color::new(200, 200, 200, 1),
+            Rarity::Uncommon => Color::new(80, 200, 80, 1),
+            Rarity::Rare => Color::new(80, 120, 255, 1),
+            Rarity::Legendary => Color::new(255, 170, 0, 1),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LootEntry {
+    // id of the item type
+    pub item: String,
+    // chance compared to the other entries of the table
+    pub weight: u32,
+    #[serde(default)]
+    pub rarity: Rarity,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LootTable {
+    pub id: String,
+    pub entries: Vec<LootEntry>,
+}
+
+impl LootTable {
+    // one entry by weight, None for tables without any weight
+    pub fn roll(&self) -> Option<&LootEntry> {
+        let total: u32 = self.entries.iter().map(|e| e.weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rand::thread_rng().gen_range(0..total);
+        for entry in self.entries.iter() {
+            if pick < entry.weight {
+                return Some(entry);
+            }
+            pick -= entry.weight;
+        }
+        None
+    }
+}
+
+// collectables from a table that come back after being picked up
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LootSpawner {
+    pub x: Float,
+    pub y: Float,
+    pub table: String,
+    // ticks until the next collectable after one was picked up
+    pub respawn: usize,
+    #[serde(default = "catalogue::one")]
+    pub count: usize,
+    #[serde(default)]
+    pub spread: Option<(Float, Float)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Loot {
+    pub tables: Vec<LootTable>,
+    #[serde(default)]
+    pub spawners: Vec<LootSpawner>,
+}
+
+impl Loot {
+    // validated against the catalogue the game already has instead of parsing another one
+    pub fn builtin(catalogue: &ItemCatalogue) -> Self {
+        Self::parse(BUILTIN, catalogue).expect("builtin loot")
+    }
+    pub fn load<P: AsRef<Path>>(path: P, catalogue: &ItemCatalogue) -> Result<Self, String> {
+        let json = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::parse(&json, catalogue)
+    }
+    pub fn parse(json: &str, catalogue: &ItemCatalogue) -> Result<Self, String> {
+        let loot: Loot = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        loot.validate(catalogue)?;
+        Ok(loot)
+    }
+    // tables can only name items of the catalogue
+    pub fn validate(&self, catalogue: &ItemCatalogue) -> Result<(), String> {
+        let mut ids = HashSet::new();
+        for table in self.tables.iter() {
+            if !ids.insert(table.id.as_str()) {
+                return Err(format!("loot table {} exists twice", table.id));
+            }
+            if table.entries.iter().all(|e| e.weight == 0) {
+                return Err(format!("loot table {} has no weight", table.id));
+            }
+            for entry in table.entries.iter() {
+                if catalogue.get(&entry.item).is_none() {
+                    return Err(format!("loot table {} has the unknown item {}", table.id, entry.item));
+                }
+            }
+        }
+        for spawner in self.spawners.iter() {
+            if self.table(&spawner.table).is_none() {
+                return Err(format!("loot spawner at {} {} uses the unknown table {}", spawner.x, spawner.y, spawner.table));
+            }
+            if spawner.respawn == 0 || spawner.count == 0 {
+                return Err(format!("loot spawner at {} {} never spawns", spawner.x, spawner.y));
+            }
+            if !catalogue::valid_spread(spawner.spread) {
+                return Err(format!("loot spawner at {} {} has an empty spread", spawner.x, spawner.y));
+            }
+        }
+        Ok(())
+    }
+    pub fn table(&self, id: &str) -> Option<&LootTable> {
+        self.tables.iter().find(|t| t.id == id)
+    }
+}
+
+// what a spawner puts down
+#[derive(Debug, Clone)]
+pub enum Source {
+    Item { id: String, color: (u8, u8, u8) },
+    Table(String),
+}
+
+// one place collectables appear at, from a placement of the catalogue or a loot spawner
+#[derive(Debug, Clone)]
+pub struct Spawner {
+    pub x: Float,
+    pub y: Float,
+    pub spread: Option<(Float, Float)>,
+    pub source: Source,
+    // None spawns once when the world spawns
+    pub respawn: Option<usize>,
+    // ticks since the last collectable was picked up
+    pub waited: usize,
+}
+
+impl Game {
+    pub fn spawn_collectables(&mut self) {
+        self.spawners.clear();
+        for item in self.catalogue.items.iter() {
+            for placement in item.placements.iter() {
+                for _ in 0..placement.count {
+                    self.spawners.push(Spawner {
+                        x: placement.x,
+                        y: placement.y,
+                        spread: placement.spread,
+                        source: Source::Item { id: item.id.clone(), color: placement.color },
+                        respawn: placement.respawn,
+                        waited: 0,
+                    });
+                }
+            }
+        }
+        for spawner in self.loot.spawners.iter() {
+            for _ in 0..spawner.count {
+                self.spawners.push(Spawner {
+                    x: spawner.x,
+                    y: spawner.y,
+                    spread: spawner.spread,
+                    source: Source::Table(spawner.table.clone()),
+                    respawn: Some(spawner.respawn),
+                    waited: 0,
+                });
+            }
+        }
+        for i in 0..self.spawners.len() {
+            self.spawn_from(i);
+        }
+    }
+    // a new collectable somewhere around the spawner
+    fn spawn_from(&mut self, index: usize) {
+        let spawner = &self.spawners[index];
+        let (x, y) = match spawner.spread {
+            Some(spread) => random_point((spawner.x, spawner.y), spread),
+            None => (spawner.x, spawner.y),
+        };
+        let (id, color) = match &spawner.source {
+            Source::Item { id, color: (r, g, b) } => (id.clone(), Color::new(*r as i32, *g as i32, *b as i32, 1)),
+            Source::Table(table) => match self.loot.table(table).and_then(|t| t.roll()) {
+                Some(entry) => (entry.item.clone(), entry.rarity.color()),
+                None => return,
+            },
+        };
+        let item = match self.create_item(&id) {
+            Some(i) => i,
+            None => return,
+        };
+        let mut collectable = Collectable::new(x, y, color, vec![item]);
+        collectable.spawner = Some(index);
+        self.collectables.push(collectable);
+    }
+}
+
+// spawners without a collectable count up to their respawn time
+pub fn handle_spawners(game: &mut Game) {
+    let mut present = vec![false; game.spawners.len()];
+    for collectable in game.collectables.iter() {
+        if let Some(spawner) = collectable.spawner {
+            present[spawner] = true;
+        }
+    }
+    for i in 0..game.spawners.len() {
+        let interval = match game.spawners[i].respawn {
+            Some(r) if !present[i] => r,
+            _ => continue,
+        };
+        game.spawners[i].waited += 1;
+        if game.spawners[i].waited >= interval {
+            game.spawners[i].waited = 0;
+            game.spawn_from(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod loot_tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+
+    #[test]
+    fn loot_table_test() {
+        let catalogue = ItemCatalogue::default();
+        let loot = Loot::builtin(&catalogue);
+        for table in loot.tables.iter() {
+            for _ in 0..20 {
+                assert!(table.roll().unwrap().weight > 0);
+            }
+        }
+        let unknown = r#"{"tables": [{"id": "a", "entries": [{"item": "nothing", "weight": 1}]}]}"#;
+        assert!(Loot::parse(unknown, &catalogue).is_err());
+        let weightless = r#"{"tables": [{"id": "a", "entries": [{"item": "heart", "weight": 0}]}]}"#;
+        assert!(Loot::parse(weightless, &catalogue).is_err());
+        let no_table = r#"{"tables": [], "spawners": [{"x": 0, "y": 0, "table": "a", "respawn": 10}]}"#;
+        assert!(Loot::parse(no_table, &catalogue).is_err());
+        let spread = r#"{"tables": [{"id": "a", "entries": [{"item": "heart", "weight": 1}]}], "spawners": [{"x": 0, "y": 0, "table": "a", "respawn": 10, "spread": [5, 5]}]}"#;
+        assert!(Loot::parse(spread, &catalogue).is_err());
+        let rare = r#"{"tables": [{"id": "a", "entries": [{"item": "heart", "weight": 0}, {"item": "monocle", "weight": 1, "rarity": "Rare"}]}]}"#;
+        let entry = Loot::parse(rare, &catalogue).unwrap().tables[0].roll().unwrap().clone();
+        assert_eq!((entry.item.as_str(), entry.rarity), ("monocle", Rarity::Rare));
+    }
+
+    #[test]
+    fn respawn_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.collectables.clear();
+        game.spawners = vec![
+            Spawner { x: 0.0, y: 0.0, spread: None, source: Source::Item { id: "heart".to_owned(), color: (255, 0, 0) }, respawn: Some(3), waited: 0 },
+            Spawner { x: 0.0, y: 0.0, spread: None, source: Source::Item { id: "monocle".to_owned(), color: (0, 0, 0) }, respawn: None, waited: 0 },
+        ];
+        for _ in 0..2 {
+            handle_spawners(&mut game);
+        }
+        assert!(game.collectables.is_empty());
+        handle_spawners(&mut game);
+        assert_eq!(game.collectables.len(), 1);
+        assert_eq!(game.collectables[0].spawner, Some(0));
+        // nothing new while the last one still lies there
+        for _ in 0..10 {
+            handle_spawners(&mut game);
+        }
+        assert_eq!(game.collectables.len(), 1);
+
+        // dropped items vanish, spawned ones stay
+        let mut dropped = Collectable::dropped(5000.0, 0.0, vec![game.create_item("monocle").unwrap()]);
+        dropped.despawn = Some(2);
+        game.collectables.push(dropped);
+        for _ in 0..2 {
+            crate::game::handle_collectables(&mut game);
+        }
+        assert_eq!(game.collectables.len(), 1);
+        assert_eq!(game.collectables[0].spawner, Some(0));
+    }
+}
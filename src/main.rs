@@ -2,43 +2,79 @@
 
 #![allow(warnings)]
 
-mod action;
-mod bits;
-mod collectable;
-mod color;
-mod enemy;
-mod game;
-mod gametraits;
-mod http;
-mod inventory;
-mod item;
-mod math;
-mod parser;
-mod player;
-mod server;
-mod spawner;
-mod string;
-mod vector;
-mod wall;
-mod websocket;
-
 use std::sync::mpsc::channel;
 
-use server::Server;
-
-use crate::{game::Game, server::ServerMessage};
+use dodgescape::{accounts::{Accounts, LogoutDrop}, catalogue::ItemCatalogue, console, game::Game, loot::Loot, revive::SelfRevive, server::{Server, ServerMessage}, shutdown};
 
-pub type Float = f64;
+// inventories, bindings and positions of the players across restarts
+const SAVE_PATH: &str = "./save/players.json";
+const ACCOUNTS_PATH: &str = "./save/accounts.json";
+// every item type with its effects and where it lies
+const ITEMS_PATH: &str = "./data/items.json";
+// loot tables and the spawners that use them
+const LOOT_PATH: &str = "./data/loot.json";
 
 fn main() {
+    shutdown::install_signal_handlers();
     let (sms, smr) = channel::<ServerMessage>();
 
-    let game = Game::new(smr);
-    game.start();
+    // a broken catalogue would lose items from saves, better not start
+    let catalogue = match ItemCatalogue::load(ITEMS_PATH) {
+        Ok(catalogue) => {
+            println!("{} item types loaded", catalogue.items.len());
+            catalogue
+        },
+        Err(e) => {
+            println!("invalid item catalogue {}: {}", ITEMS_PATH, e);
+            return;
+        },
+    };
+    let loot = match Loot::load(LOOT_PATH, &catalogue) {
+        Ok(loot) => {
+            println!("{} loot tables loaded", loot.tables.len());
+            loot
+        },
+        Err(e) => {
+            println!("invalid loot {}: {}", LOOT_PATH, e);
+            return;
+        },
+    };
+    let mut game = Game::with_items(smr, catalogue, loot);
+    // the shutdown would overwrite a save it couldnt read with only the online guests
+    match game.load_players(SAVE_PATH) {
+        Ok(_) => println!("{} saved players loaded", game.saved_players.len()),
+        Err(e) => {
+            println!("couldnt load saved players {}: {}", SAVE_PATH, e);
+            return;
+        },
+    }
+    // without the accounts anybody could claim their names and nothing would be saved
+    match Accounts::load(ACCOUNTS_PATH) {
+        Ok(accounts) => {
+            println!("{} accounts loaded", accounts.accounts.len());
+            game.accounts = accounts;
+        },
+        Err(e) => {
+            println!("couldnt load accounts {}: {}", ACCOUNTS_PATH, e);
+            return;
+        },
+    }
+    // accounts keep their inventory on logout, guests drop it like before
+    game.logout_drop = LogoutDrop::GuestsOnly;
+    // KeyR only respawns downed players, everyone else has to walk home
+    game.revive.self_revive = SelfRevive::Downed;
     // wsl ip
-    let server = Server::new("172.28.37.92:7878", sms);
-    // let server = Server::new("172.19.241.59:7878", sms);
+    let server = Server::new("172.28.37.92:7878", sms.clone());
+    // let server = Server::new("172.19.241.59:7878", sms.clone());
+    // the shutdown stops the listener before it answers the last messages
+    game.listener = Some(server.stop_handle());
+    let game_handle = game.start();
+    // type help for the commands
+    console::start(sms);
     let server_handle = server.start();
     println!("server started...");
+    // the game loop only ends on shutdown
+    let _ = game_handle.join();
     let _ = server_handle.join();
+    println!("server stopped");
 }
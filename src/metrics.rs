@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use crate::{game::Game, profiler::Phases};
+
+#[derive(Default)]
+pub struct PhaseStats {
+    pub phase: &'static str,
+    pub count: usize,
+    pub total: Duration,
+    pub max: Duration,
+    pub last: Duration,
+}
+
+// everything the game loop counts while running, rendered as plaintext for the /metrics route
+#[derive(Default)]
+pub struct Metrics {
+    pub ticks: usize,
+    pub tick: PhaseStats,
+    pub phases: Vec<PhaseStats>,
+    pub connections: usize,
+    pub snapshots_sent: usize,
+    pub bytes_sent: usize,
+}
+
+impl Metrics {
+    pub fn record_phase(&mut self, phase: &'static str, time: Duration) {
+        let stats = match self.phases.iter().position(|s| {s.phase == phase}) {
+            Some(i) => self.phases.get_mut(i).unwrap(),
+            None => {
+                self.phases.push(PhaseStats { phase, ..Default::default() });
+                self.phases.last_mut().unwrap()
+            },
+        };
+        stats.add(time);
+    }
+    pub fn record_tick(&mut self, time: Duration, phases: &Phases) {
+        self.ticks += 1;
+        self.tick.add(time);
+        for (phase, time) in phases.iter() {
+            self.record_phase(phase, *time);
+        }
+    }
+    pub fn record_snapshot(&mut self, bytes: usize) {
+        self.snapshots_sent += 1;
+        self.bytes_sent += bytes;
+    }
+    pub fn render(&self, game: &Game) -> String {
+        let mut out = "".to_owned();
+        out.push_str(&format!("dodgescape_ticks_total {}\n", self.ticks));
+        out.push_str(&format!("dodgescape_tick_seconds_total {}\n", self.tick.total.as_secs_f64()));
+        out.push_str(&format!("dodgescape_tick_seconds_max {}\n", self.tick.max.as_secs_f64()));
+        out.push_str(&format!("dodgescape_tick_seconds_last {}\n", self.tick.last.as_secs_f64()));
+        for stats in self.phases.iter() {
+            out.push_str(&format!("dodgescape_phase_calls_total{{phase=\"{}\"}} {}\n", stats.phase, stats.count));
+            out.push_str(&format!("dodgescape_phase_seconds_total{{phase=\"{}\"}} {}\n", stats.phase, stats.total.as_secs_f64()));
+            out.push_str(&format!("dodgescape_phase_seconds_max{{phase=\"{}\"}} {}\n", stats.phase, stats.max.as_secs_f64()));
+            out.push_str(&format!("dodgescape_phase_seconds_last{{phase=\"{}\"}} {}\n", stats.phase, stats.last.as_secs_f64()));
+        }
+        out.push_str(&format!("dodgescape_connections {}\n", self.connections));
+        out.push_str(&format!("dodgescape_snapshots_sent_total {}\n", self.snapshots_sent));
+        out.push_str(&format!("dodgescape_bytes_sent_total {}\n", self.bytes_sent));
+        out.push_str(&format!("dodgescape_players {}\n", game.players.len()));
+        out.push_str(&format!("dodgescape_collectables {}\n", game.collectables.len()));
+        for (g, group) in game.enemies.iter().enumerate() {
+            out.push_str(&format!("dodgescape_enemies{{group=\"{}\"}} {}\n", g, group.1.len()));
+        }
+        out.push_str(&format!("dodgescape_enemies_awake {}\n", game.lod.awake));
+        out.push_str(&format!("dodgescape_enemies_sleeping {}\n", game.lod.sleeping));
+        // updates the lod saved, the phase times show what they cost when enemies are awake
+        out.push_str(&format!("dodgescape_enemy_updates_skipped {}\n", game.lod.skipped));
+        out.push_str(&format!("dodgescape_enemy_updates_skipped_total {}\n", game.lod.skipped_total));
+        out
+    }
+}
+
+impl PhaseStats {
+    pub fn add(&mut self, time: Duration) {
+        self.count += 1;
+        self.total += time;
+        self.last = time;
+        if time > self.max {
+            self.max = time;
+        }
+    }
+}
+
+#[cfg(test)]
+mod metrics_tests {
+    use super::*;
+
+    #[test]
+    fn record_phase_test() {
+        let mut metrics = Metrics::default();
+        metrics.record_tick(Duration::from_millis(5), &vec![("lod", Duration::from_millis(1)), ("collision", Duration::from_millis(3))]);
+        metrics.record_tick(Duration::from_millis(2), &vec![("lod", Duration::from_millis(2)), ("collision", Duration::from_millis(1))]);
+        assert_eq!(metrics.ticks, 2);
+        assert_eq!(metrics.tick.max, Duration::from_millis(5));
+        let lod = metrics.phases.iter().find(|s| {s.phase == "lod"}).unwrap();
+        assert_eq!(lod.count, 2);
+        assert_eq!(lod.total, Duration::from_millis(3));
+        assert_eq!(lod.max, Duration::from_millis(2));
+        assert_eq!(lod.last, Duration::from_millis(2));
+    }
+}
@@ -0,0 +1,104 @@
+use std::thread;
+
+use crate::{enemy::Enemy, game::Enemies, wall::WallType};
+
+pub type Group = (Vec<WallType>, Vec<Enemy>);
+
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+// spread the groups over the threads so every thread gets about the same amount of enemies
+// groups are very different in size (flowers vs explosions) so splitting by group count isnt enough
+pub fn partition(sizes: &[usize], threads: usize) -> Vec<Vec<usize>> {
+    let threads = threads.max(1).min(sizes.len().max(1));
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|a, b| sizes[*b].cmp(&sizes[*a]).then(a.cmp(b)));
+    let mut buckets: Vec<(usize, Vec<usize>)> = vec![(0, vec![]); threads];
+    for g in order {
+        let bucket = buckets.iter_mut().min_by_key(|(load, _)| *load).unwrap();
+        bucket.0 += sizes[g];
+        bucket.1.push(g);
+    }
+    buckets.into_iter().map(|(_, groups)| groups).filter(|groups| !groups.is_empty()).collect()
+}
+
+// runs f for every group, each group is touched by exactly one thread
+pub fn for_each_group<F>(enemies: &mut Enemies, threads: usize, f: F)
+where
+    F: Fn(usize, &mut Group) + Sync,
+{
+    if threads <= 1 || enemies.len() <= 1 {
+        for (g, group) in enemies.iter_mut().enumerate() {
+            f(g, group);
+        }
+        return;
+    }
+    let sizes: Vec<usize> = enemies.iter().map(|group| group.1.len()).collect();
+    let buckets = partition(&sizes, threads);
+    let mut groups: Vec<Option<&mut Group>> = enemies.iter_mut().map(Some).collect();
+    let mut work: Vec<Vec<(usize, &mut Group)>> = vec![];
+    for bucket in buckets.iter() {
+        work.push(bucket.iter().map(|g| (*g, groups[*g].take().unwrap())).collect());
+    }
+    let f = &f;
+    thread::scope(|s| {
+        for groups in work {
+            s.spawn(move || {
+                for (g, group) in groups {
+                    f(g, group);
+                }
+            });
+        }
+    });
+}
+
+// runs f for every group and returns the results in group order, no matter which thread computed them
+pub fn map_groups<R, F>(enemies: &Enemies, threads: usize, f: F) -> Vec<R>
+where
+    R: Send,
+    F: Fn(usize, &Group) -> R + Sync,
+{
+    if threads <= 1 || enemies.len() <= 1 {
+        return enemies.iter().enumerate().map(|(g, group)| f(g, group)).collect();
+    }
+    let sizes: Vec<usize> = enemies.iter().map(|group| group.1.len()).collect();
+    let buckets = partition(&sizes, threads);
+    let f = &f;
+    let mut results: Vec<(usize, R)> = thread::scope(|s| {
+        let handles: Vec<_> = buckets.iter().map(|bucket| {
+            s.spawn(move || {
+                bucket.iter().map(|g| (*g, f(*g, &enemies[*g]))).collect::<Vec<(usize, R)>>()
+            })
+        }).collect();
+        handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+    });
+    results.sort_by_key(|(g, _)| *g);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+#[cfg(test)]
+mod parallel_tests {
+    use super::*;
+
+    #[test]
+    fn partition_test() {
+        let sizes = vec![1650, 60, 360, 120, 60];
+        let buckets = partition(&sizes, 2);
+        assert_eq!(buckets, vec![vec![0], vec![2, 3, 1, 4]]);
+    }
+
+    #[test]
+    fn partition_more_threads_than_groups() {
+        let buckets = partition(&[3, 5], 8);
+        assert_eq!(buckets, vec![vec![1], vec![0]]);
+    }
+
+    #[test]
+    fn map_groups_keeps_order() {
+        let enemies: Enemies = (0..10).map(|g| (vec![], (0..g * 3).map(|_| Enemy::default()).collect())).collect();
+        let serial = map_groups(&enemies, 1, |g, group| (g, group.1.len()));
+        let threaded = map_groups(&enemies, 4, |g, group| (g, group.1.len()));
+        assert_eq!(serial, threaded);
+    }
+}
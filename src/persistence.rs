@@ -0,0 +1,208 @@
+use std::{collections::HashMap, fs, io, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{catalogue, game::Game, input::Controls, item::{Activation, Item}, player::Player, stats::Stats, Float};
+
+// items are stored by their type and built again from the catalogue on restore,
+// only the state that can change while playing is kept
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedItem {
+    // older saves have the display name here
+    #[serde(alias = "name")]
+    pub kind: String,
+    pub active: bool,
+    // charges left, older saves counted the uses of consumables
+    #[serde(alias = "uses")]
+    pub charges: Option<usize>,
+    #[serde(default)]
+    pub cooldown_left: usize,
+    #[serde(default = "catalogue::one")]
+    pub count: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedPlayer {
+    pub x: Float,
+    pub y: Float,
+    pub items: Vec<SavedItem>,
+    // key to the position of the item in items
+    pub bindings: HashMap<String, usize>,
+    #[serde(default)]
+    pub discovered: Vec<String>,
+    #[serde(default)]
+    pub stats: Stats,
+    #[serde(default)]
+    pub waypoints: Vec<String>,
+    #[serde(default)]
+    pub checkpoint: Option<String>,
+    #[serde(default)]
+    pub controls: Controls,
+}
+
+impl SavedPlayer {
+    pub fn from_player(player: &Player) -> Self {
+        Self {
+            x: player.x,
+            y: player.y,
+            items: player.inventory.items.iter().map(|item| SavedItem {
+                kind: item.kind.clone(),
+                active: item.active,
+                charges: item.charges.map(|_| item.charges_left),
+                cooldown_left: item.cooldown_left,
+                count: item.count,
+            }).collect(),
+            bindings: player.inventory.bindings.iter()
+                .filter_map(|(key, id)| player.inventory.index_of(*id).map(|i| (key.clone(), i)))
+                .collect(),
+            discovered: player.discovered.clone(),
+            stats: player.stats.clone(),
+            waypoints: player.waypoints.clone(),
+            checkpoint: player.checkpoint.clone(),
+            controls: player.controls.clone(),
+        }
+    }
+}
+
+impl Game {
+    // reads the saved players and remembers the path for save_players, a missing file is an empty save.
+    // a file that cant be read is never overwritten
+    pub fn load_players<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        match fs::read_to_string(&path) {
+            Ok(data) => {
+                self.saved_players = serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+            Err(e) => return Err(e),
+        }
+        self.save_path = Some(path.as_ref().to_path_buf());
+        Ok(())
+    }
+    // writes the online guests and everyone who didnt log in since the last start, accounts are saved in their own file
+    pub fn save_players(&self) -> io::Result<usize> {
+        let path: &PathBuf = match &self.save_path {
+            Some(p) => p,
+            None => return Ok(0),
+        };
+        let mut saved = self.saved_players.clone();
+        for player in self.players.iter().filter(|p| !p.account) {
+            saved.insert(player.name.clone(), SavedPlayer::from_player(player));
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // write to a temporary file first so a crash while saving keeps the old save
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&saved).unwrap())?;
+        fs::rename(&tmp, path)?;
+        Ok(saved.len())
+    }
+    pub fn save_accounts(&mut self) -> io::Result<()> {
+        for player in self.players.iter().filter(|p| p.account) {
+            self.accounts.store(&player.name, SavedPlayer::from_player(player));
+        }
+        self.accounts.save()
+    }
+    // gives a logging in guest back what was saved for the name, the save is used up.
+    // only accounts keep their stats, a guest starts counting again
+    pub fn restore_player(&mut self, player: &mut Player) {
+        let mut saved = match self.saved_players.remove(&player.name) {
+            Some(s) => s,
+            None => return,
+        };
+        saved.stats = Stats::default();
+        self.apply_saved(player, saved);
+    }
+    pub fn apply_saved(&mut self, player: &mut Player, saved: SavedPlayer) {
+        player.x = saved.x;
+        player.y = saved.y;
+        player.old_position = (saved.x, saved.y);
+        // ids of the restored items by their position in the save, for the bindings
+        let mut ids = vec![None; saved.items.len()];
+        for (i, saved_item) in saved.items.iter().enumerate() {
+            let mut item = match self.create_item(&saved_item.kind) {
+                Some(i) => i,
+                None => {
+                    println!("saved item {} of {} doesnt exist anymore", saved_item.kind, player.name);
+                    continue;
+                },
+            };
+            item.count = saved_item.count.clamp(1, item.stack);
+            // a use in progress ends on logout
+            item.active = saved_item.active && item.activation == Activation::Toggle;
+            item.cooldown_left = saved_item.cooldown_left.min(item.cooldown);
+            match (item.charges, saved_item.charges) {
+                (Some(charges), Some(saved)) => item.charges_left = saved.min(charges.max),
+                _ => {},
+            }
+            ids[i] = Some(item.id);
+            // saves are not limited by the capacity, nothing a player had gets lost
+            player.inventory.items.push(item);
+        }
+        player.inventory.bindings = saved.bindings.into_iter().filter_map(|(key, i)| Some((key, (*ids.get(i)?)?))).collect();
+        player.discovered = saved.discovered;
+        player.stats = saved.stats;
+        player.waypoints = saved.waypoints;
+        player.checkpoint = saved.checkpoint;
+        player.controls = saved.controls;
+    }
+}
+
+#[cfg(test)]
+mod persistence_tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+
+    #[test]
+    fn save_restore_test() {
+        let path = std::env::temp_dir().join(format!("dodgescape_save_test_{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.load_players(&path).unwrap();
+        let name = "jo".to_owned();
+        let mut player = Player::new(&name);
+        player.x = 120.0;
+        player.y = -40.0;
+        let mut heart = game.create_item("heart").unwrap();
+        heart.charges_left = 1;
+        let heart_id = heart.id;
+        player.inventory.items.push(game.create_item("monocle").unwrap());
+        player.inventory.items.push(heart);
+        player.inventory.items[0].active = true;
+        player.inventory.bindings.insert("KeyH".to_owned(), heart_id);
+        player.controls.bind("GamepadA", crate::input::InputAction::DropItem).unwrap();
+        game.players.push(player);
+        assert_eq!(game.save_players().unwrap(), 1);
+
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.load_players(&path).unwrap();
+        let mut player = Player::new(&name);
+        game.restore_player(&mut player);
+        assert_eq!((player.x, player.y), (120.0, -40.0));
+        assert_eq!(player.inventory.items.len(), 2);
+        assert!(player.inventory.items[0].active);
+        assert_eq!(player.inventory.items[1].charges_left, 1);
+        assert_eq!(player.inventory.bindings.get("KeyH"), Some(&player.inventory.items[1].id));
+        assert_eq!(player.controls.bindings.get("GamepadA"), Some(&crate::input::InputAction::DropItem));
+        // a save is only restored once
+        assert!(game.saved_players.is_empty());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn corrupt_save_test() {
+        let path = std::env::temp_dir().join(format!("dodgescape_corrupt_test_{}.json", std::process::id()));
+        fs::write(&path, "{not json").unwrap();
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        assert!(game.load_players(&path).is_err());
+        game.players.push(Player::new(&"jo".to_owned()));
+        // the broken file stays for somebody to repair
+        assert_eq!(game.save_players().unwrap(), 0);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{not json");
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -1,14 +1,16 @@
 
 
-use crate::{action::Action, collectable::Collectable, color::Color, game::{DrawPack, Game, Shape, Walls}, impl_Drawable, impl_Entity, impl_Moveable, impl_Position, inventory::Inventory, vector, wall::WallType, Float};
+use crate::{action::Action, collectable::Collectable, color::Color, game::{DrawPack, Game, Shape, Walls}, impl_Drawable, impl_Entity, impl_Moveable, impl_Position, inventory::Inventory, item::HEART, vector, wall::WallType, Float};
 use crate::gametraits::*;
-use crate::{impl_RadiusTrait};
+use crate::{impl_RadiusTrait, input::{Controls, InputAction}, stats::Stats};
 
 #[derive(Clone, Copy, Debug)]
 pub enum PlayerEffect {
     Shrink {origin: usize, shrink: Float, ease: usize},
     SpeedAlter {origin: usize, slow: Float, ease: usize},
-    Harden {ease: usize, cooldown: usize, speed: Float},
+    Harden {ease: usize, speed: Float},
+    // after a revive, see revive.rs
+    Invulnerable {ease: usize},
 }
 
 #[derive(Default)]
@@ -36,6 +38,29 @@ pub struct Player {
     pub zoomlimit: (Float, Float),
     pub just_collided: bool,
     pub old_position: (Float, Float),
+    // logged in with an account, see accounts.rs
+    pub account: bool,
+    // names of the map areas the player has been in
+    pub discovered: Vec<String>,
+    pub stats: Stats,
+    pub leaderboard_open: bool,
+    // ticks left until a downed player respawns at home
+    pub downed: usize,
+    // ticks a teammate has been reviving this player
+    pub revive_progress: usize,
+    // inside a spawn area, see safezone.rs
+    pub safe: bool,
+    // names of the found waypoints in the order they were found, see waypoint.rs
+    pub waypoints: Vec<String>,
+    // the last waypoint visited, respawns happen there
+    pub checkpoint: Option<String>,
+    pub waypoint_menu_open: bool,
+    // the menu shows WAYPOINT_ROWS waypoints starting at waypoint_page * WAYPOINT_ROWS
+    pub waypoint_page: usize,
+    // which keys trigger which actions, see input.rs
+    pub controls: Controls,
+    // direction and strength from a stick or touch joystick, already checked
+    pub analog: Option<(Float, Float)>,
 }
 
 impl_Entity!(Player);
@@ -62,176 +87,122 @@ impl Player {
 
         p
     }
-    fn scroll_use(&mut self) {
-        self.alive = true;
-        let scroll = self.inventory.items.iter().position(|e| {e.name == "teleportation scroll".to_owned()}).unwrap();
-        self.inventory.items.remove(scroll);
-    }
-    fn tp_possibility(&mut self, target: (Float, Float), key: &str) {
-        let scroll = self.inventory.items.iter().position(|e| {e.name == "teleportation scroll".to_owned()});
-        match scroll {
-            Some(_) => {
-                let key = key.to_owned();
-                if self.just_pressed.contains(&key) {
-                    self.x = target.0;
-                    self.y = target.1;
-                    self.scroll_use();
-                }
-            },
-            None => {},
-        };
-    }
     fn handle_respawn(&mut self) {
-        let key = "KeyR".to_owned();
-        if self.keys_down.contains(&key) {
-            self.x = 0.0;
-            self.y = 0.0;
-            self.alive = true;
-        }
-        
-        let spawnd = 11000.0;
-        self.tp_possibility((-spawnd, -spawnd), "Digit1");
-        self.tp_possibility((spawnd, -spawnd), "Digit2");
-        self.tp_possibility((-spawnd, spawnd), "Digit3");
-        self.tp_possibility((spawnd, spawnd), "Digit4");
-
-        let key = "KeyQ".to_owned();
-        if self.just_pressed.contains(&key) {
-            let heart = self.inventory.items.iter_mut().find(|e| {e.name == "heart"});
+        // respawns are handled with the downed state in revive.rs, teleports with the waypoints in waypoint.rs
+        if self.triggered(InputAction::UseHeart) {
+            let heart = self.inventory.items.iter().position(|e| {e.kind == HEART});
             match heart {
                 Some(heart) => {
-                    heart.active = true;
+                    self.inventory.use_item(heart);
                 },
                 None => {},
             };
             // self.alive = true;
         }
     }
-    fn handle_inventory(&mut self, collectables: &mut Vec<Collectable>) {
+    fn handle_inventory(&mut self, collectables: &mut Vec<Collectable>, item_counter: &mut usize) {
+        self.inventory.full = self.inventory.full.saturating_sub(1);
         // handle keybindings
         if !self.inventory.bind_mode {
             for key in self.just_pressed.iter() {
-                let b = self.inventory.bindings.get(key);
+                let b = self.inventory.bindings.get(key).and_then(|id| self.inventory.index_of(*id));
                 match b {
                     Some(i) => {
-                        let item = self.inventory.items.get_mut(*i);
-                        match item {
-                            Some(item) => {
-                                item.active = !item.active;
-                            },
-                            None => {},
-                        }
+                        self.inventory.use_item(i);
                     },
                     None => {},
                 }
             }
+            // ids first, a used up item would move the indices of the ones after it
+            let triggered: Vec<usize> = (0..self.inventory.items.len())
+                .filter(|i| self.triggered(InputAction::UseItem(*i)))
+                .map(|i| self.inventory.items[i].id)
+                .collect();
+            for id in triggered {
+                if let Some(i) = self.inventory.index_of(id) {
+                    self.inventory.use_item(i);
+                }
+            }
         }
 
-        let key = "KeyE".to_owned();
-        if self.just_pressed.contains(&key) {
+        if self.triggered(InputAction::ToggleInventory) {
             self.inventory.open = !self.inventory.open;
         }
-        let key = "KeyC".to_owned();
-        if self.just_pressed.contains(&key) {
+        if self.triggered(InputAction::ToggleLeaderboard) {
+            self.leaderboard_open = !self.leaderboard_open;
+        }
+        if self.triggered(InputAction::CollectAll) {
             for c in collectables.iter_mut() {
                 c.collect(self);
             }
-            collectables.clear();
+            collectables.retain(|c| !c.items.is_empty());
         }
-        let key = "KeyG".to_owned();
-        if self.just_pressed.contains(&key) {
-            match &mut self.inventory.selected_item {
-                Some(i) => {
-                    let mut item = self.inventory.items.remove(*i);
-                    if self.inventory.items.len() == 0 {
-                        self.inventory.selected_item = None;
-                    }
-                    else if *i == self.inventory.items.len() {
-                        *i -= 1;
-                    }
+        if self.triggered(InputAction::DropItem) {
+            match self.inventory.selected_item.and_then(|i| self.inventory.take_one(i, item_counter)) {
+                Some(mut item) => {
                     item.active = false;
-                    let collectable = Collectable::new(self.x, self.y + 50.0, Color::new(0, 0, 255, 1), vec![item]);
+                    let collectable = Collectable::dropped(self.x, self.y + 50.0, vec![item]);
                     collectables.push(collectable);
                 },
                 None => {},
             };
         }
         if self.inventory.open {
-            if self.inventory.items.len() > 0 {
-                match &mut self.inventory.selected_item {
-                    None => {
-                        self.inventory.selected_item = Some(0);
-                    },
-                    Some(s) => {
-                        let key = "KeyB".to_owned();
-                        if self.just_pressed.contains(&key) {
-                            if self.inventory.bind_mode {
-                                self.inventory.bind_mode = false;
-                                self.inventory.bindings.clear();
-                            }
-                            else {
-                                self.inventory.bind_mode = true;
-                            }
+            if self.controls.any(&self.just_pressed, InputAction::SortInventory) {
+                self.inventory.sort();
+            }
+            if self.controls.any(&self.just_pressed, InputAction::FilterInventory) {
+                self.inventory.cycle_filter();
+            }
+            if self.inventory.selected_item.is_none() {
+                self.inventory.select(0);
+            }
+            match self.inventory.selected_item {
+                None => {},
+                Some(selected) => {
+                    if self.controls.any(&self.just_pressed, InputAction::BindItem) {
+                        if self.inventory.bind_mode {
+                            self.inventory.bind_mode = false;
+                            self.inventory.bindings.clear();
                         }
                         else {
-                            if self.inventory.bind_mode {
-                                let key = "Escape".to_owned();
-                                if self.just_pressed.contains(&key) {
-                                    self.inventory.bind_mode = false;
-                                }
-                                else {
-                                    let key = self.just_pressed.get(0);
-                                    match key {
-                                        Some(binding) => {
-                                            self.inventory.bind_mode = false;
-                                            self.inventory.bindings.insert(binding.clone(), *s);
-                                        },
-                                        None => {},
-                                    }
-                                }
-                            }
-                        }
-
-                        let key = "ArrowDown".to_owned();
-                        if self.just_pressed.contains(&key) {
-                            if *s == self.inventory.items.len() - 1 {
-                                *s = 0;
-                            }
-                            else {
-                                *s += 1;
-                            }
+                            self.inventory.bind_mode = true;
                         }
-                        let key = "ArrowUp".to_owned();
-                        if self.just_pressed.contains(&key) {
-                            if *s == 0 {
-                                *s = self.inventory.items.len() - 1;
+                    }
+                    else {
+                        if self.inventory.bind_mode {
+                            if self.controls.any(&self.just_pressed, InputAction::Cancel) {
+                                self.inventory.bind_mode = false;
                             }
                             else {
-                                *s -= 1;
+                                let key = self.just_pressed.get(0);
+                                match key {
+                                    Some(binding) => {
+                                        self.inventory.bind_mode = false;
+                                        let id = self.inventory.items[selected].id;
+                                        self.inventory.bindings.insert(binding.clone(), id);
+                                    },
+                                    None => {},
+                                }
                             }
                         }
-                        let key = "ArrowRight".to_owned();
-                        if self.just_pressed.contains(&&key) {
-                            let item = self.inventory.items.get_mut(*s);
-                            match item {
-                                Some(item) => {
-                                    item.active = true;
-                                },
-                                None => {},
-                            }
+                    }
+
+                    if self.controls.any(&self.just_pressed, InputAction::SelectNext) {
+                        self.inventory.select(1);
+                    }
+                    if self.controls.any(&self.just_pressed, InputAction::SelectPrevious) {
+                        self.inventory.select(-1);
+                    }
+                    if let Some(selected) = self.inventory.selected_item {
+                        if self.controls.any(&self.just_pressed, InputAction::ActivateItem) {
+                            self.inventory.set_active(selected, true);
                         }
-                        let key = "ArrowLeft".to_owned();
-                        if self.just_pressed.contains(&&key) {
-                            let item = self.inventory.items.get_mut(*s);
-                            match item {
-                                Some(item) => {
-                                    item.active = false;
-                                },
-                                None => {},
-                            }
+                        if self.controls.any(&self.just_pressed, InputAction::DeactivateItem) {
+                            self.inventory.set_active(selected, false);
                         }
-                    },
-                }
+                    }
+                },
             }
         }
         else {
@@ -242,39 +213,48 @@ impl Player {
     fn handle_movement(&mut self) {
         let mut vx = 0.0;
         let mut vy = 0.0;
-        let key = "Space".to_owned();
-        if self.keys_down.contains(&key) {
+        if self.held(InputAction::FollowMouse) {
             vx = self.mouse.0 as Float / 50.0 * self.speed;
             vy = self.mouse.1 as Float / 50.0 * self.speed;
         }
         else {
-            let key = "KeyW".to_owned();
-            if self.keys_down.contains(&key) {
+            if self.held(InputAction::MoveUp) {
                 vy += -self.speed;
             }
-            let key = "KeyS".to_owned();
-            if self.keys_down.contains(&key) {
+            if self.held(InputAction::MoveDown) {
                 vy += self.speed;
             }
-            let key = "KeyD".to_owned();
-            if self.keys_down.contains(&key) {
+            if self.held(InputAction::MoveRight) {
                 vx += self.speed;
             }
-            let key = "KeyA".to_owned();
-            if self.keys_down.contains(&key) {
+            if self.held(InputAction::MoveLeft) {
                 vx += -self.speed;
             }
+            // the stick only counts while no movement key is down
+            if (vx, vy) == (0.0, 0.0) {
+                if let Some((ax, ay)) = self.analog {
+                    vx = ax * self.speed;
+                    vy = ay * self.speed;
+                }
+            }
         }
         if vector::abs((vx, vy)) > self.speed {
             (vx, vy) = vector::normalize((vx, vy), self.speed);  
         }
         // slowing down
-        let key = "ShiftLeft".to_owned();
-        if self.keys_down.contains(&key) {
+        if self.held(InputAction::Slow) {
             self.speed_multiplier *= 0.5;
         }
         self.velocity = (vx, vy);
     }
+    // one of the keys bound to the action is down
+    pub fn held(&self, action: InputAction) -> bool {
+        self.controls.any(&self.keys_down, action)
+    }
+    // one of the keys bound to the action was pressed this tick
+    pub fn triggered(&self, action: InputAction) -> bool {
+        self.controls.any(&self.just_pressed, action)
+    }
     pub fn get_just_pressed(&mut self) -> Vec<String> {
         let mut jp = vec![];
         for key in self.keys_down.iter() {
@@ -284,10 +264,10 @@ impl Player {
         }
         jp
     }
-    pub fn handle_keys(&mut self, collectables: &mut Vec<Collectable>) {
+    pub fn handle_keys(&mut self, collectables: &mut Vec<Collectable>, item_counter: &mut usize) {
         self.just_pressed = self.get_just_pressed();
         self.handle_respawn();
-        self.handle_inventory(collectables);
+        self.handle_inventory(collectables, item_counter);
         self.handle_movement();
         self.old_keys_down = self.keys_down.clone();
     }
@@ -307,7 +287,10 @@ pub fn handle_effects(game: &mut Game) {
                     }
                     else {
                         *ease -= 1;
-                        actions.push((p, Action::MulPlayerSpeedMultiplier { f: *slow }));
+                        // wears off in a safe zone without slowing
+                        if !player.safe {
+                            actions.push((p, Action::MulPlayerSpeedMultiplier { f: *slow }));
+                        }
                     }
                 },
                 PlayerEffect::Shrink { origin, shrink, ease } => {
@@ -317,23 +300,29 @@ pub fn handle_effects(game: &mut Game) {
                     }
                     else {
                         *ease -= 1;
-                        actions.push((p, Action::MulPlayerRadiusMultiplier { f: *shrink }));
+                        if !player.safe {
+                            actions.push((p, Action::MulPlayerRadiusMultiplier { f: *shrink }));
+                        }
                     }
                 },
-                PlayerEffect::Harden { ease, cooldown, speed } => {
-                    if *cooldown == 0 {
-                        // remove this effect
+                PlayerEffect::Harden { ease, speed } => {
+                    if *ease == 0 {
+                        // the cooldown is on the item
                         deletions.push((p, Action::RemovePlayerEffect { effect: e }));
                     }
                     else {
-                        *cooldown -= 1;
+                        *ease -= 1;
+                        actions.push((p, Action::MulPlayerSpeedMultiplier { f: *speed }));
+                        actions.push((p, Action::SetPlayerInvincible(true)));
                     }
+                },
+                PlayerEffect::Invulnerable { ease } => {
                     if *ease == 0 {
-                        // effect shouldnt work anymore
+                        // remove this effect
+                        deletions.push((p, Action::RemovePlayerEffect { effect: e }));
                     }
                     else {
                         *ease -= 1;
-                        actions.push((p, Action::MulPlayerSpeedMultiplier { f: *speed }));
                         actions.push((p, Action::SetPlayerInvincible(true)));
                     }
                 },
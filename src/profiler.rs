@@ -0,0 +1,26 @@
+use std::time::{Duration, Instant};
+
+pub type Phases = Vec<(&'static str, Duration)>;
+
+// measures the time between laps, every lap is one phase of the tick
+pub struct PhaseTimer {
+    last: Instant,
+    pub phases: Phases,
+}
+
+impl PhaseTimer {
+    pub fn start() -> Self {
+        Self {
+            last: Instant::now(),
+            phases: vec![],
+        }
+    }
+    pub fn lap(&mut self, phase: &'static str) {
+        let now = Instant::now();
+        self.phases.push((phase, now - self.last));
+        self.last = now;
+    }
+    pub fn finish(self) -> Phases {
+        self.phases
+    }
+}
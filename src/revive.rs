@@ -0,0 +1,198 @@
+use crate::{game::{distance, Game}, gametraits::{Position, RadiusTrait}, input::InputAction, player::{Player, PlayerEffect}, Float};
+
+// where players without a checkpoint end up after giving up or bleeding out
+pub const HOME: (Float, Float) = (0.0, 0.0);
+
+// what KeyR does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelfRevive {
+    // brings the player home alive at any time, like before the downed state existed
+    Always,
+    // only a downed player can give up and respawn at home
+    Downed,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReviveSettings {
+    // ticks a downed player waits for help before respawning at home, 0 waits forever
+    pub downed_ticks: usize,
+    // ticks a teammate has to stay in contact for a revive
+    pub revive_ticks: usize,
+    // ticks a revived player cant be killed
+    pub invulnerable_ticks: usize,
+    // same after logging in or respawning at home
+    pub spawn_invulnerable_ticks: usize,
+    pub self_revive: SelfRevive,
+}
+
+impl Default for ReviveSettings {
+    fn default() -> Self {
+        Self {
+            downed_ticks: 900,
+            revive_ticks: 60,
+            invulnerable_ticks: 90,
+            spawn_invulnerable_ticks: 150,
+            self_revive: SelfRevive::Always,
+        }
+    }
+}
+
+pub fn make_invulnerable(player: &mut Player, ticks: usize) {
+    if ticks > 0 {
+        player.effects.push(PlayerEffect::Invulnerable { ease: ticks });
+    }
+}
+
+fn revive(player: &mut Player, settings: &ReviveSettings) {
+    player.alive = true;
+    player.downed = 0;
+    player.revive_progress = 0;
+    make_invulnerable(player, settings.invulnerable_ticks);
+}
+
+fn respawn(player: &mut Player, settings: &ReviveSettings, checkpoint: (Float, Float)) {
+    (player.x, player.y) = checkpoint;
+    player.alive = true;
+    player.downed = 0;
+    player.revive_progress = 0;
+    make_invulnerable(player, settings.spawn_invulnerable_ticks);
+}
+
+// downed players count down, get revived by a teammate staying in contact or give up with KeyR
+pub fn handle_revives(game: &mut Game) {
+    let settings = game.revive;
+    let helpers: Vec<Option<usize>> = game.players.iter().map(|player| {
+        if player.alive {return None;}
+        game.players.iter().position(|other| {
+            !std::ptr::eq(player, other) && other.alive && distance(player, other).2 <= player.get_radius() + other.get_radius()
+        })
+    }).collect();
+    let checkpoints: Vec<(Float, Float)> = game.players.iter().map(|p| game.checkpoint_position(p)).collect();
+    for (i, helper) in helpers.into_iter().enumerate() {
+        let checkpoint = checkpoints[i];
+        let player = game.players.get_mut(i).unwrap();
+        let give_up = player.held(InputAction::Respawn) && match settings.self_revive {
+            SelfRevive::Always => true,
+            SelfRevive::Downed => !player.alive,
+            SelfRevive::Never => false,
+        };
+        if give_up {
+            if player.alive {
+                (player.x, player.y) = checkpoint;
+            }
+            else {
+                respawn(player, &settings, checkpoint);
+            }
+            continue;
+        }
+        // revived some other way, like by a heart
+        if player.alive {
+            player.downed = 0;
+            player.revive_progress = 0;
+            continue;
+        }
+        // just died
+        if player.downed == 0 {
+            player.downed = settings.downed_ticks;
+        }
+        match helper {
+            Some(h) => {
+                player.revive_progress += 1;
+                if player.revive_progress >= settings.revive_ticks {
+                    revive(player, &settings);
+                    player.stats.revives_received += 1;
+                    game.players[h].stats.revives_given += 1;
+                    continue;
+                }
+            },
+            None => player.revive_progress = 0,
+        }
+        if settings.downed_ticks == 0 {continue;}
+        if player.downed <= 1 {
+            respawn(player, &settings, checkpoint);
+        }
+        else {
+            player.downed -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod revive_tests {
+    use std::sync::mpsc::channel;
+
+    use crate::game::handle_kill_revive;
+
+    use super::*;
+
+    fn game(players: &[(&str, Float)]) -> Game {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.enemies.clear();
+        for (name, x) in players {
+            let mut player = Player::new(&name.to_string());
+            player.x = *x;
+            // normally set by the player effects every tick
+            player.radius_multiplier = 1.0;
+            game.players.push(player);
+        }
+        game
+    }
+
+    #[test]
+    fn timed_revive_test() {
+        let mut game = game(&[("down", 0.0), ("helper", 10.0)]);
+        game.revive.revive_ticks = 3;
+        game.players[0].alive = false;
+        handle_kill_revive(&mut game);
+        handle_kill_revive(&mut game);
+        assert!(!game.players[0].alive);
+        assert_eq!(game.players[0].revive_progress, 2);
+        // losing contact starts over
+        game.players[1].x = 500.0;
+        handle_kill_revive(&mut game);
+        assert_eq!(game.players[0].revive_progress, 0);
+        game.players[1].x = 10.0;
+        for _ in 0..3 {
+            handle_kill_revive(&mut game);
+        }
+        assert!(game.players[0].alive);
+        assert_eq!(game.players[1].stats.revives_given, 1);
+        assert!(game.players[0].effects.iter().any(|e| matches!(e, PlayerEffect::Invulnerable { .. })));
+    }
+
+    #[test]
+    fn bleed_out_test() {
+        let mut game = game(&[("down", 5000.0)]);
+        game.revive.downed_ticks = 3;
+        game.players[0].alive = false;
+        for _ in 0..2 {
+            handle_kill_revive(&mut game);
+        }
+        assert!(!game.players[0].alive);
+        handle_kill_revive(&mut game);
+        assert!(game.players[0].alive);
+        assert_eq!(game.players[0].x, HOME.0);
+    }
+
+    #[test]
+    fn self_revive_test() {
+        let mut game = game(&[("player", 5000.0)]);
+        game.players[0].keys_down = vec!["KeyR".to_owned()];
+        game.revive.self_revive = SelfRevive::Downed;
+        // alive players cant teleport home
+        handle_kill_revive(&mut game);
+        assert_eq!(game.players[0].x, 5000.0);
+        game.players[0].alive = false;
+        handle_kill_revive(&mut game);
+        assert!(game.players[0].alive);
+        assert_eq!(game.players[0].x, HOME.0);
+
+        game.revive.self_revive = SelfRevive::Never;
+        game.players[0].x = 5000.0;
+        game.players[0].alive = false;
+        handle_kill_revive(&mut game);
+        assert!(!game.players[0].alive);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{game::Game, gametraits::Position, vector};
+
+// players inside a spawn area cant be killed or hit by enemy effects, enemies bounce off its walls
+pub fn handle_safe_zones(game: &mut Game) {
+    for player in game.players.iter_mut() {
+        let position = (player.get_x(), player.get_y());
+        player.safe = game.areas.iter().any(|(walltype, corners)| {
+            walltype.is_safe_zone() && vector::point_in_polygon(position, corners)
+        });
+        // reset with the other player effects every tick
+        if player.safe {
+            player.invincible = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod safezone_tests {
+    use std::sync::mpsc::channel;
+
+    use crate::{enemy::Enemy, game::{handle_collision, handle_kill_revive}, player::Player, wall::WallType};
+
+    use super::*;
+
+    #[test]
+    fn safe_zone_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.enemies.clear();
+        let mut player = Player::new(&"safe".to_owned());
+        player.radius_multiplier = 1.0;
+        game.players.push(player);
+        handle_safe_zones(&mut game);
+        assert!(game.players[0].safe && game.players[0].invincible);
+
+        // an enemy on the player doesnt kill it
+        let mut enemy = Enemy::new(0.0, 0.0, (0.0, 0.0), 30.0, "red");
+        enemy.radius_multiplier = 1.0;
+        game.enemies.push((vec![], vec![enemy]));
+        handle_kill_revive(&mut game);
+        assert!(game.players[0].alive);
+
+        game.players[0].x = 5000.0;
+        handle_safe_zones(&mut game);
+        assert!(!game.players[0].safe);
+    }
+
+    #[test]
+    fn enemies_cant_enter_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.enemies.clear();
+        // heading straight into spawn a, the group doesnt list spawn walls
+        let mut enemy = Enemy::new(-900.0, 0.0, (40.0, 0.0), 20.0, "red");
+        enemy.radius_multiplier = 1.0;
+        enemy.speed_multiplier = 1.0;
+        game.enemies.push((vec![WallType::Dirt], vec![enemy]));
+        for _ in 0..40 {
+            crate::game::handle_movements(&mut game);
+            handle_collision(&mut game);
+        }
+        let enemy = &game.enemies[0].1[0];
+        let (_, corners) = game.areas.iter().find(|(w, _)| *w == WallType::SpawnA).unwrap();
+        assert!(!vector::point_in_polygon((enemy.x, enemy.y), corners));
+    }
+}
@@ -1,24 +1,53 @@
 use std::{
     fs,
     io::{Read, Write},
-    net::{TcpListener, TcpStream},
-    sync::mpsc::{self, Sender},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{atomic::{AtomicBool, Ordering}, mpsc::{self, Sender}, Arc},
     thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
-use crate::{http::Http_request, Float};
+use crate::{accounts::{Credential, Proof}, admin::{self, AdminCommand, AdminError}, http::Http_request, input::InputAction, Float};
 
 use serde::{Deserialize, Serialize};
 
+// how long a stopped server waits for its connections to close
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// what the game sends to a websocket connection
+#[derive(Debug, Clone)]
+pub enum Outgoing {
+    Frame(String),
+    // close frame with a reason, ends the connection
+    Close(String),
+}
+
 #[derive(Debug, Clone)]
 pub enum ServerMessage {
-    Login(String, Sender<String>),
+    // sent by the connection after it checked the password, see accounts::prove
+    Login(String, Option<Proof>, Sender<Outgoing>),
+    // the stored hash of an account for a connection that checks a password
+    PasswordHash(String, Sender<Option<String>>),
     Logout(String),
     Input {
         name: String,
         mouse: (Float, Float),
         keys: Vec<String>,
         wheel: i32,
+        // stick or touch joystick, checked in input::analog
+        movement: Option<(Float, Float)>,
+    },
+    // plaintext metrics of the running game for the /metrics route
+    Metrics(Sender<String>),
+    // json leaderboard for the /leaderboard route
+    Leaderboard(Sender<String>),
+    // admin api command, answered with the json result or an error message
+    Admin(AdminCommand, Sender<Result<String, String>>),
+    // changes the controls of a player, no key resets them to the defaults and no action unbinds the key
+    Bind {
+        name: String,
+        key: Option<String>,
+        action: Option<InputAction>,
     },
 }
 
@@ -30,8 +59,29 @@ pub struct ClientMessage {
     pub y: Option<Float>,
     pub keys_down: Option<Vec<String>>,
     pub wheel: Option<i32>,
+    // only sent with the login
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    // analog movement with a length up to 1, only sent with game
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub movement: Option<(Float, Float)>,
+    // only sent with bind
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<InputAction>,
 }
 impl ClientMessage {
+    // a stored token wins over the password
+    pub fn credential(&self) -> Option<Credential> {
+        match (&self.token, &self.password) {
+            (Some(token), _) if !token.is_empty() => Some(Credential::Token(token.clone())),
+            (_, Some(password)) if !password.is_empty() => Some(Credential::Password(password.clone())),
+            _ => None,
+        }
+    }
     pub fn new(
         mode: String,
         username: String,
@@ -47,6 +97,11 @@ impl ClientMessage {
             y,
             keys_down,
             wheel,
+            password: None,
+            token: None,
+            movement: None,
+            key: None,
+            action: None,
         }
     }
 }
@@ -54,6 +109,23 @@ impl ClientMessage {
 pub struct Server {
     listener: TcpListener,
     sender: mpsc::Sender<ServerMessage>,
+    // bearer token for the /admin routes, taken from DODGESCAPE_ADMIN_TOKEN
+    admin_token: Option<String>,
+    stop: Arc<AtomicBool>,
+}
+
+// stops the listener thread of a started server
+pub struct StopHandle {
+    stop: Arc<AtomicBool>,
+    address: SocketAddr,
+}
+
+impl StopHandle {
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // the listener only notices the flag on the next connection
+        let _ = TcpStream::connect(self.address);
+    }
 }
 
 impl Server {
@@ -64,12 +136,29 @@ impl Server {
         let server = Server {
             listener: TcpListener::bind(address).unwrap(),
             sender,
+            admin_token: std::env::var("DODGESCAPE_ADMIN_TOKEN").ok().filter(|t| !t.is_empty()),
+            stop: Arc::new(AtomicBool::new(false)),
         };
+        if server.admin_token.is_none() {
+            println!("no DODGESCAPE_ADMIN_TOKEN set, admin api disabled");
+        }
         server
     }
+    pub fn stop_handle(&self) -> StopHandle {
+        StopHandle {
+            stop: self.stop.clone(),
+            address: self.listener.local_addr().unwrap(),
+        }
+    }
     pub fn start(self) -> JoinHandle<()> {
         thread::spawn(move || {
+            let mut connections: Vec<JoinHandle<()>> = vec![];
             for stream in self.listener.incoming() {
+                if self.stop.load(Ordering::SeqCst) {
+                    println!("server stopped accepting connections");
+                    break;
+                }
+                connections.retain(|c| !c.is_finished());
                 let stream = match stream {
                     Ok(result) => result,
                     Err(_) => {
@@ -79,14 +168,22 @@ impl Server {
                 };
                 // println!("conntection incoming");
                 let sender = self.sender.clone();
+                let admin_token = self.admin_token.clone();
 
-                thread::spawn(move || {
-                    Self::handle_connection(sender, stream);
-                });
+                connections.push(thread::spawn(move || {
+                    Self::handle_connection(sender, stream, admin_token);
+                }));
+            }
+            // nothing can queue up on the socket while the open connections close
+            drop(self.listener);
+            // give the open connections time to send their close frames
+            let deadline = Instant::now() + CLOSE_TIMEOUT;
+            while connections.iter().any(|c| !c.is_finished()) && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
             }
         })
     }
-    fn handle_connection(sender: mpsc::Sender<ServerMessage>, mut stream: TcpStream) {
+    fn handle_connection(sender: mpsc::Sender<ServerMessage>, mut stream: TcpStream, admin_token: Option<String>) {
         let received: String = Server::receive(&mut stream);
 
         let request = match Http_request::parse(&received) {
@@ -105,7 +202,7 @@ impl Server {
                 (crate::websocket::response(key), vec![])
             }
             None => {
-                let (status_line, contents) = Self::handle_response(&request);
+                let (status_line, contents) = Self::handle_response(&request, &sender, &admin_token);
                 (format!(
                     "{}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Headers: content-type\r\n\r\n",
                     status_line,
@@ -135,12 +232,49 @@ impl Server {
         received.push_str(&msg);
         received
     }
-    fn handle_response(request: &Http_request) -> (String, Vec<u8>) {
+    // sends a message to the game thread and waits for its answer
+    fn query<T, F: FnOnce(Sender<T>) -> ServerMessage>(sender: &mpsc::Sender<ServerMessage>, message: F) -> Option<T> {
+        let (answer_sender, answer_receiver) = mpsc::channel::<T>();
+        sender.send(message(answer_sender)).ok()?;
+        answer_receiver.recv_timeout(Duration::from_secs(1)).ok()
+    }
+    fn handle_admin(request: &Http_request, sender: &mpsc::Sender<ServerMessage>, admin_token: &Option<String>) -> (&'static str, String) {
+        if !admin::authorized(request, admin_token) {
+            return ("HTTP/1.1 401 UNAUTHORIZED", "{\"error\":\"unauthorized\"}".to_owned());
+        }
+        let mut parts = request.request_line.split_whitespace();
+        let method = parts.next().unwrap_or("");
+        let route = parts.next().unwrap_or("").trim_start_matches("/admin/");
+        let command = match AdminCommand::parse(method, route, &request.body.join("\n")) {
+            Ok(c) => c,
+            Err(AdminError::NotFound) => return ("HTTP/1.1 404 NOT FOUND", "{\"error\":\"unknown command\"}".to_owned()),
+            Err(AdminError::BadRequest(e)) => return ("HTTP/1.1 400 BAD REQUEST", serde_json::json!({"error": e}).to_string()),
+        };
+        match Self::query(sender, |answer| ServerMessage::Admin(command, answer)) {
+            Some(Ok(result)) => ("HTTP/1.1 200 OK", result),
+            Some(Err(e)) => ("HTTP/1.1 400 BAD REQUEST", serde_json::json!({"error": e}).to_string()),
+            None => ("HTTP/1.1 503 SERVICE UNAVAILABLE", "{\"error\":\"game not responding\"}".to_owned()),
+        }
+    }
+    fn handle_response(request: &Http_request, sender: &mpsc::Sender<ServerMessage>, admin_token: &Option<String>) -> (String, Vec<u8>) {
+        if request.request_line.split_whitespace().nth(1).unwrap_or("").starts_with("/admin/") {
+            let (status_line, response) = Self::handle_admin(request, sender, admin_token);
+            return (status_line.to_owned(), response.into());
+        }
         // getting the output
         let (status_line, response): (&str, Vec<u8>) = match request.request_line.as_str() {
             // "POST / HTTP/1.1" => ("HTTP/1.1 200 OK", objects.into()),
             "OPTIONS / HTTP/1.1" => ("HTTP/1.1 200 OK", "".to_owned().into()),
 
+            "GET /metrics HTTP/1.1" => match Self::query(sender, ServerMessage::Metrics) {
+                Some(metrics) => ("HTTP/1.1 200 OK", metrics.into()),
+                None => ("HTTP/1.1 503 SERVICE UNAVAILABLE", "game not responding".to_owned().into()),
+            },
+            "GET /leaderboard HTTP/1.1" => match Self::query(sender, ServerMessage::Leaderboard) {
+                Some(leaderboard) => ("HTTP/1.1 200 OK", leaderboard.into()),
+                None => ("HTTP/1.1 503 SERVICE UNAVAILABLE", "game not responding".to_owned().into()),
+            },
+
             "GET / HTTP/1.1" => ("HTTP/1.1 200 OK", fs::read("./res/hello.html").unwrap()),
             "GET /bg.png HTTP/1.1" => ("HTTP/1.1 200 OK", fs::read("./res/bg.png").unwrap()),
             "GET /icon.png HTTP/1.1" => ("HTTP/1.1 200 OK", fs::read("./res/icon.png").unwrap()),
@@ -245,3 +379,30 @@ mod serde_test {
         assert_eq!(serialized, "{\"color\":\"rgb(0,32,15)\",\"shape\":{\"Circle\":{\"radius\":{\"Absolute\":30.3}}},\"offset\":[0.0,0.0]}".to_owned());
     }
 }
+
+#[cfg(test)]
+mod shutdown_tests {
+    use std::{net::TcpStream, sync::mpsc::channel};
+
+    use crate::{admin::AdminCommand, game::Game};
+
+    use super::*;
+
+    #[test]
+    fn listener_stops_first_test() {
+        let (sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        let server = Server::new("127.0.0.1:0", sender.clone());
+        let address = server.listener.local_addr().unwrap();
+        game.listener = Some(server.stop_handle());
+        let server_handle = server.start();
+        let game_handle = game.start();
+        let (answer_sender, answer_receiver) = channel();
+        sender.send(ServerMessage::Admin(AdminCommand::Shutdown, answer_sender)).unwrap();
+        answer_receiver.recv().unwrap().unwrap();
+        game_handle.join().unwrap();
+        // the game stopped the listener itself, nobody else does in here
+        server_handle.join().unwrap();
+        assert!(TcpStream::connect(address).is_err());
+    }
+}
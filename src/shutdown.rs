@@ -0,0 +1,30 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// set from the signal handler or the admin api, the game loop polls it every frame
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+pub fn request() {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+#[cfg(unix)]
+extern "C" fn handle_signal(_signal: libc::c_int) {
+    // only async signal safe work in here, everything else happens in the game loop
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+// ctrl+c and kill shut the server down instead of killing it right away
+#[cfg(unix)]
+pub fn install_signal_handlers() {
+    unsafe {
+        libc::signal(libc::SIGINT, handle_signal as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, handle_signal as libc::sighandler_t);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install_signal_handlers() {}
@@ -1,27 +1,70 @@
 use rand::Rng;
 
-use crate::{collectable::Collectable, color::Color, enemy::{Enemy, EnemyEffect}, game::{DrawPack, Game, Shape}, gametraits::Radius, item::{Item, ItemEffect}, vector::random_point, wall::{Wall, WallType}, Float};
+use crate::{enemy::{Enemy, EnemyEffect}, game::{DrawPack, Game, Shape}, gametraits::Radius, wall::{Wall, WallType}, Float};
+
+// multipliers for the amount and the speed of all enemies
+const SPAWN_M: i32 = 3;
+const SPEED_M: Float = 15.0;
+// every enemy group in spawn order
+pub const ENEMY_GROUPS: [&str; 15] = ["dirt", "wind", "flower", "water", "fire", "blackhole", "tech", "snake", "explosion", "ice", "lightning", "poison", "candy", "hypnosis", "hell"];
 
 impl Game {
     pub fn spawn_enemies(&mut self) {
-        let spawn_m = 3;
-        let speed_m = 15.0;
-        self.spawn_dirt_enemies(speed_m, spawn_m);
-        self.spawn_wind_enemies(speed_m, spawn_m);
-        self.spawn_flower_enemies(speed_m, spawn_m);
-        self.spawn_water_enemies(speed_m, spawn_m);
-        self.spawn_fire_enemies(speed_m, spawn_m);
-        self.spawn_blackhole_enemies(speed_m, spawn_m);
-        self.spawn_tech_enemies(speed_m, spawn_m);
-        self.spawn_snake_enemies(speed_m, spawn_m);
-        self.spawn_explosion_enemies(speed_m, spawn_m);
-        self.spawn_ice_enemies(speed_m, spawn_m);
-        self.spawn_lightning_enemies(speed_m, spawn_m);
-        self.spawn_poison_enemies(speed_m, spawn_m);
-        self.spawn_candy_enemies(speed_m, spawn_m);
-        self.spawn_hypnosis_enemies(speed_m, spawn_m);
-        self.spawn_hell_enemies(speed_m, spawn_m);
-        // self.spawn_test_enemies(speed_m, spawn_m);
+        for name in ENEMY_GROUPS {
+            self.spawn_enemy_group(name);
+        }
+        // self.spawn_test_enemies(SPEED_M, SPAWN_M);
+
+        // enemy groups that bounce off each other
+        // candies and hypnosis share the candy area
+        let pairs = [("dirt", "dirt"), ("wind", "wind"), ("flower", "flower"), ("water", "water"), ("poison", "poison"), ("candy", "candy"), ("candy", "hypnosis")];
+        for (a, b) in pairs {
+            match (self.enemy_group(a), self.enemy_group(b)) {
+                (Some(a), Some(b)) => self.enemy_collisions.push((a, b)),
+                _ => {},
+            }
+        }
+    }
+    // pushes a new group with the given name, false if there is no group with that name
+    pub fn spawn_enemy_group(&mut self, name: &str) -> bool {
+        let (speed_m, spawn_m) = (SPEED_M, SPAWN_M);
+        match name {
+            "dirt" => self.spawn_dirt_enemies(speed_m, spawn_m),
+            "wind" => self.spawn_wind_enemies(speed_m, spawn_m),
+            "flower" => self.spawn_flower_enemies(speed_m, spawn_m),
+            "water" => self.spawn_water_enemies(speed_m, spawn_m),
+            "fire" => self.spawn_fire_enemies(speed_m, spawn_m),
+            "blackhole" => self.spawn_blackhole_enemies(speed_m, spawn_m),
+            "tech" => self.spawn_tech_enemies(speed_m, spawn_m),
+            "snake" => self.spawn_snake_enemies(speed_m, spawn_m),
+            "explosion" => self.spawn_explosion_enemies(speed_m, spawn_m),
+            "ice" => self.spawn_ice_enemies(speed_m, spawn_m),
+            "lightning" => self.spawn_lightning_enemies(speed_m, spawn_m),
+            "poison" => self.spawn_poison_enemies(speed_m, spawn_m),
+            "candy" => self.spawn_candy_enemies(speed_m, spawn_m),
+            "hypnosis" => self.spawn_hypnosis_enemies(speed_m, spawn_m),
+            "hell" => self.spawn_hell_enemies(speed_m, spawn_m),
+            _ => return false,
+        }
+        self.enemy_groups.push(name.to_owned());
+        true
+    }
+    pub fn enemy_group(&self, name: &str) -> Option<usize> {
+        self.enemy_groups.iter().position(|g| {g == name})
+    }
+    // replaces group g with a freshly spawned one, the index stays the same so collision pairs stay valid
+    pub fn respawn_enemy_group(&mut self, g: usize) -> bool {
+        let name = match self.enemy_groups.get(g) {
+            Some(name) => name.clone(),
+            None => return false,
+        };
+        if !self.spawn_enemy_group(&name) {
+            return false;
+        }
+        self.enemy_groups.pop();
+        let group = self.enemies.pop().unwrap();
+        self.enemies[g] = group;
+        true
     }
     pub fn spawn_test_enemies(&mut self, speed_m: Float, spawn_m: i32) {
         let ids = vec![WallType::SpawnA];
@@ -292,6 +335,7 @@ impl Game {
             enemy.draw_packs.push(DrawPack::new(color, Shape::Circle { radius: Radius::Relative(0.8) }, (cloudradius, cloudradius / 5.0)));
             enemy.draw_packs.push(DrawPack::new(color, Shape::Circle { radius: Radius::Relative(0.7) }, (-cloudradius, cloudradius / 4.0)));
             enemy.harmless = true;
+            enemy.solid = false;
             let cd = rand::thread_rng().gen_range(400..=500);
             let lightning_aura_radius = Radius::Relative(5.0);
             enemy.effects.push(EnemyEffect::Explode {
@@ -443,6 +487,7 @@ impl Game {
                 },
             }
         }
+        self.areas.push((walltype, corners.clone()));
         let poly = Shape::Poly { corners };
         let draw_pack = DrawPack::new(color, poly, (0.0, 0.0));
         self.map.push((start, draw_pack));
@@ -519,178 +564,15 @@ impl Game {
         let corners = vec![(6.0,-6.0),(5.0,-4.0),(4.0,-5.0)]
             .iter().map(|e| {(e.0 * multiplier, e.1 * multiplier)}).collect();
         self.spawn_area(corners, spawncolor, WallType::SpawnB, default);
+
+        // checkpoints in the spawns
+        self.spawn_waypoint("spawn", (0.0, 0.0), 300.0);
+        self.spawn_waypoint("north west", (-5.0 * multiplier, -5.0 * multiplier), 400.0);
+        self.spawn_waypoint("north east", (5.0 * multiplier, -5.0 * multiplier), 400.0);
+        self.spawn_waypoint("south west", (-5.0 * multiplier, 5.0 * multiplier), 400.0);
+        self.spawn_waypoint("south east", (5.0 * multiplier, 5.0 * multiplier), 400.0);
         
         // grid
         self.spawn_grid(40000.0, "rgb(255,255,255,0.05)", 500.0, 10.0);
     }
-    pub fn spawn_collectables(&mut self) {
-        let scale = 0.3;
-        let mut item_counter = 0;
-        let collectables = vec![
-            Collectable::new(-11000.0, -11000.0, Color::new(200, 200, 100, 1), vec![
-                Item::new("teleportation scroll", vec![], vec![], &mut item_counter, None ),
-            ]),
-            Collectable::new(11000.0, -11000.0, Color::new(200, 200, 100, 1), vec![
-                Item::new("teleportation scroll", vec![], vec![], &mut item_counter, None ),
-            ]),
-            Collectable::new(-11000.0, 11000.0, Color::new(200, 200, 100, 1), vec![
-                Item::new("teleportation scroll", vec![], vec![], &mut item_counter, None ),
-            ]),
-            Collectable::new(11000.0, 11000.0, Color::new(200, 200, 100, 1), vec![
-                Item::new("teleportation scroll", vec![], vec![], &mut item_counter, None ),
-            ]),
-            Collectable::new(2000.0, 2000.0, Color::new(200, 200, 100, 1), vec![
-                Item::new("monocle",
-                    vec![ItemEffect::Vision((0.9,0.9))],
-                    vec![], &mut item_counter,
-                    Some(DrawPack::new("", Shape::Image { keyword: "monocle".to_owned(), scale }, (0.0, 0.0)))
-                )
-            ]),
-            Collectable::new(200.0, 200.0, Color::new(200, 200, 100, 1), vec![
-                Item::new("heart",
-                    vec![
-                        ItemEffect::Revive { radius: Radius::Relative(5.0) },
-                        ItemEffect::Consumable { uses: 3 },
-                    ],
-                    vec![], &mut item_counter,
-                    Some(DrawPack::new("", Shape::Image { keyword: "heart".to_owned(), scale }, (0.0, 0.0)))
-                )
-            ]),
-            Collectable::new(0.0, -2000.0, Color::new(200, 200, 100, 1), vec![
-                Item::new("microscope", vec![
-                    ItemEffect::Vision((1.0,5.0)),
-                ], vec![], &mut item_counter,
-                    Some(DrawPack::new("", Shape::Image { keyword: "microscope".to_owned(), scale }, (0.0, 0.0)))
-                )
-            ]),
-            Collectable::new(0.0, 20000.0, Color::new(50, 50, 50, 1), vec![
-                Item::new("bunker", vec![
-                    ItemEffect::Harden { limit: 50, cooldown: 300, speed: 0.0 },
-                    ItemEffect::Usable,
-                ], vec![ ], &mut item_counter,
-                    None
-                )
-            ]),
-            Collectable::new(-22000.0, -22000.0, Color::new(50, 50, 50, 1), vec![
-                Item::new("sugar rush", vec![
-                    ItemEffect::Harden { limit: 10, cooldown: 100, speed: 3.0 },
-                    ItemEffect::Usable,
-                ], vec![ ], &mut item_counter,
-                    None
-                )
-            ]),
-            Collectable::new(4000.0, -4000.0, Color::new(255, 255, 255, 1), vec![
-                Item::new("binoculars", vec![ItemEffect::Vision((0.7,1.0))], vec![], &mut item_counter,
-                    Some(DrawPack::new("", Shape::Image { keyword: "binoculars".to_owned(), scale }, (0.0, 0.0)))
-                )
-            ]),
-            Collectable::new(-6000.0, 0.0, Color::new(200, 200, 0, 1), vec![
-                Item::new("telescope", vec![ItemEffect::Vision((0.4,0.6))], vec![], &mut item_counter,
-                    Some(DrawPack::new("", Shape::Image { keyword: "telescope".to_owned(), scale }, (0.0, 0.0)))
-                )
-            ]),
-            Collectable::new(17500.0, -17500.0, Color::new(255,0,0,1), vec![
-                Item::new("heatwave", vec![
-                    ItemEffect::SlowEnemies { power: 0.5, radius: Radius::Relative(7.0), duration: 100 },
-                ], vec![
-                    DrawPack::new("rgba(255,0,0,0.2)", Shape::Circle { radius: Radius::Relative(7.0) }, (0.0, 0.0))
-                ], &mut item_counter,
-                    Some(DrawPack::new("", Shape::Image { keyword: "heatwave".to_owned(), scale }, (0.0, 0.0)))
-                )
-            ]),
-            Collectable::new(0.0, -16500.0, Color::new(255,0,0,1), vec![
-                Item::new("blizzard", vec![
-                    ItemEffect::SlowEnemies { power: 0.8, radius: Radius::Relative(20.0), duration: 1 },
-                ], vec![
-                    DrawPack::new("rgba(100,100,255,0.2)", Shape::Circle { radius: Radius::Relative(20.0) }, (0.0, 0.0))
-                ], &mut item_counter,
-                    Some(DrawPack::new("", Shape::Image { keyword: "blizzard".to_owned(), scale }, (0.0, 0.0)))
-                )
-            ]),
-            Collectable::new(-9000.0, 14500.0, Color::new(255,0,0,1), vec![
-                Item::new("univeye", vec![
-                    ItemEffect::Vision((0.01,1.0)),
-                ], vec![ ], &mut item_counter,
-                    Some(DrawPack::new("", Shape::Image { keyword: "univeye".to_owned(), scale }, (0.0, 0.0)))
-                )
-            ]),
-            Collectable::new(-4500.0, -4000.0, Color::new(255,0,0,1), vec![
-                Item::new("puddle", vec![
-                ], vec![ ], &mut item_counter,
-                    Some(DrawPack::new("", Shape::Image { keyword: "puddle".to_owned(), scale }, (0.0, 0.0)))
-                )
-            ]),
-            Collectable::new(-4000.0, 5500.0, Color::new(255,0,0,1), vec![
-                Item::new("windaura", vec![
-                    ItemEffect::PushEnemies { power: 2.0, radius: Radius::Relative(5.0) },
-                ], vec![
-                    DrawPack::new("rgba(255,255,255,0.2)", Shape::Circle { radius: Radius::Relative(5.0) }, (0.0, 0.0))
-                ], &mut item_counter,
-                    Some(DrawPack::new("", Shape::Image { keyword: "push".to_owned(), scale }, (0.0, 0.0)))
-                )
-            ]),
-            Collectable::new(4000.0, 4000.0, Color::new(255,0,0,1), vec![
-                Item::new("sandstorm", vec![
-                    ItemEffect::ShrinkEnemies { power: 0.5, radius: Radius::Relative(7.0), duration: 100 },
-                ], vec![
-                    DrawPack::new("rgba(50,40,20,0.2)", Shape::Circle { radius: Radius::Relative(7.0) }, (0.0, 0.0))
-                ], &mut item_counter,
-                    None
-                )
-            ]),
-            Collectable::new(-9000.0, -14000.0, Color::new(255,0,0,1), vec![
-                Item::new("hourglass", vec![
-                ], vec![
-                    DrawPack::new("rgba(0,255,0,0.2)", Shape::Circle { radius: Radius::Relative(7.0) }, (0.0, 0.0))
-                ], &mut item_counter,
-                    Some(DrawPack::new("", Shape::Image { keyword: "hourglass".to_owned(), scale }, (0.0, 0.0)))
-                )
-            ]),
-            Collectable::new(8000.0, -12500.0, Color::new(255,0,0,1), vec![
-                Item::new("orbit", vec![
-                    ItemEffect::RotateEnemies { power: 1.0, radius: Radius::Relative(12.0) },
-                ], vec![
-                    DrawPack::new("rgba(150,0,255,0.2)", Shape::Circle { radius: Radius::Relative(12.0) }, (0.0, 0.0))
-                ], &mut item_counter,
-                    Some(DrawPack::new("", Shape::Image { keyword: "orbit".to_owned(), scale }, (0.0, 0.0)))
-                )
-            ]),
-            Collectable::new(11000.0, 16000.0, Color::new(255,0,0,1), vec![
-                Item::new("blackhole", vec![
-                ], vec![ ], &mut item_counter,
-                    Some(DrawPack::new("", Shape::Image { keyword: "blackhole".to_owned(), scale }, (0.0, 0.0)))
-                )
-            ]),
-            Collectable::new(6000.0, -6000.0, Color::new(255,0,0,1), vec![
-                Item::new("speedup", vec![
-                    ItemEffect::SlowEnemies { power: 3.0, radius: Radius::Relative(15.0), duration: 1 },
-                    ItemEffect::Speed(3.0),
-                ], vec![
-                    DrawPack::new("rgba(0,0,255,0.2)", Shape::Circle { radius: Radius::Relative(15.0) }, (0.0, 0.0))
-                ], &mut item_counter,
-                    Some(DrawPack::new("", Shape::Image { keyword: "speedup".to_owned(), scale }, (0.0, 0.0)))
-                )
-            ]),
-        ];
-        for c in collectables {
-            self.collectables.push(c);
-        }
-        // across the fire area
-        let cords = vec![(8,0),(-8,0),(0,8),(0,-8)];
-        for c in cords {
-            for i in 0..10 {
-                let center = (c.0 as Float * 1000.0, c.1 as Float * 1000.0);
-                let distance = (0.0, 2000.0);
-                let point = random_point(center, distance);
-                let c = Collectable::new(point.0, point.1, Color::new(255,0,0,1), vec![
-                    Item::new("dragonfire rune", vec![
-                        ItemEffect::Speed(1.1),
-                    ], vec![], &mut item_counter,
-                        Some(DrawPack::new("", Shape::Image { keyword: "dragonfirerune".to_owned(), scale }, (0.0, 0.0)))
-                    )
-                ]);
-                self.collectables.push(c);
-            }
-        }
-    }
 }
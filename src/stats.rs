@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{game::Game, gametraits::Position, vector, Float};
+
+// entries shown in the ingame leaderboard
+pub const LEADERBOARD_ROWS: usize = 10;
+
+// progression of a player, saved with the account
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct Stats {
+    pub logins: usize,
+    pub ticks_played: usize,
+    pub ticks_alive: usize,
+    pub deaths: usize,
+    pub revives_given: usize,
+    pub revives_received: usize,
+    pub items_collected: usize,
+    pub distance: Float,
+    // farthest distance from the spawn and the area it was reached in
+    pub farthest: Float,
+    pub farthest_area: Option<String>,
+}
+
+pub fn handle_stats(game: &mut Game) {
+    for player in game.players.iter_mut() {
+        player.stats.ticks_played += 1;
+        if player.alive {
+            player.stats.ticks_alive += 1;
+        }
+        let position = (player.get_x(), player.get_y());
+        let farthest = vector::abs(position);
+        if farthest <= player.stats.farthest {continue;}
+        player.stats.farthest = farthest;
+        // areas are drawn over each other, the last one containing the point is the visible one
+        if let Some((walltype, _)) = game.areas.iter().rev().find(|(_, corners)| vector::point_in_polygon(position, corners)) {
+            player.stats.farthest_area = Some(format!("{:?}", walltype));
+        }
+    }
+}
+
+impl Game {
+    // online players and accounts that played before, the farthest first
+    pub fn leaderboard(&self) -> Vec<(String, bool, Stats)> {
+        let mut entries: Vec<(String, bool, Stats)> = self.players.iter()
+            .map(|p| (p.name.clone(), true, p.stats.clone()))
+            .collect();
+        for (name, account) in self.accounts.accounts.iter() {
+            if entries.iter().any(|e| e.0 == *name) {continue;}
+            if let Some(player) = &account.player {
+                entries.push((name.clone(), false, player.stats.clone()));
+            }
+        }
+        entries.sort_by(|a, b| {
+            b.2.farthest.total_cmp(&a.2.farthest).then_with(|| a.2.deaths.cmp(&b.2.deaths)).then_with(|| a.0.cmp(&b.0))
+        });
+        entries
+    }
+    pub fn leaderboard_json(&self) -> Value {
+        Value::Array(self.leaderboard().into_iter().map(|(name, online, stats)| json!({
+            "name": name,
+            "online": online,
+            "stats": stats,
+        })).collect())
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use std::sync::mpsc::channel;
+
+    use crate::{game::handle_kill_revive, player::Player};
+
+    use super::*;
+
+    #[test]
+    fn kill_revive_stats_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.enemies.clear();
+        game.revive.revive_ticks = 1;
+        for name in ["dead", "helper"] {
+            game.players.push(Player::new(&name.to_owned()));
+        }
+        game.players[0].alive = false;
+        game.players[0].stats.deaths = 1;
+        handle_kill_revive(&mut game);
+        assert!(game.players[0].alive);
+        assert_eq!(game.players[0].stats.revives_received, 1);
+        assert_eq!(game.players[1].stats.revives_given, 1);
+        // touching an alive player is no revive
+        handle_kill_revive(&mut game);
+        assert_eq!(game.players[1].stats.revives_given, 1);
+    }
+
+    #[test]
+    fn leaderboard_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        for (name, x) in [("near", 100.0), ("far", -3000.0)] {
+            let mut player = Player::new(&name.to_owned());
+            player.x = x;
+            game.players.push(player);
+        }
+        handle_stats(&mut game);
+        let leaderboard = game.leaderboard();
+        assert_eq!(leaderboard[0].0, "far");
+        assert_eq!(leaderboard[0].2.farthest, 3000.0);
+        assert_eq!(leaderboard[1].0, "near");
+        assert_eq!(game.leaderboard_json()[0]["stats"]["ticks_alive"], 1);
+    }
+}
@@ -1,5 +1,7 @@
 pub trait StringOperations {
     fn substring(&self, start: usize, end: usize) -> String;
+    // breaks between words so no line is longer than width, unless a single word is
+    fn wrap(&self, width: usize) -> Vec<String>;
 }
 
 impl StringOperations for String {
@@ -11,6 +13,24 @@ impl StringOperations for String {
         }
         subs
     }
+    fn wrap(&self, width: usize) -> Vec<String> {
+        let mut lines: Vec<String> = vec![];
+        let mut line = "".to_owned();
+        for word in self.split_whitespace() {
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+                lines.push(line);
+                line = "".to_owned();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+        lines
+    }
 }
 
 #[cfg(test)]
@@ -23,5 +43,11 @@ mod string_tests {
         let e = "llo ";
         assert_eq!(r, e);
     }
+    #[test]
+    fn wrap() {
+        let string = "hello my name is jonsa".to_owned();
+        assert_eq!(string.wrap(8), vec!["hello my", "name is", "jonsa"]);
+        assert_eq!("".to_owned().wrap(8), Vec::<String>::new());
+    }
 }
 
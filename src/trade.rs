@@ -0,0 +1,310 @@
+use crate::{game::Game, gametraits::Position, input::InputAction, inventory::FULL_TICKS, item::Item, player::Player, vector, Float};
+
+// players further apart than this cant ask for a trade and running away ends it
+pub const TRADE_DISTANCE: Float = 300.0;
+
+// a trade between two players, it starts as a request of from to to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+    pub from: String,
+    pub to: String,
+    // to accepted the request, the offer window is shown
+    pub open: bool,
+    // the items each side gives, from first
+    pub offers: [Vec<Offer>; 2],
+    // any change of the offers takes the confirmations back
+    pub confirmed: [bool; 2],
+}
+
+// an offered item as it was when the other side last saw it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Offer {
+    pub id: usize,
+    pub count: usize,
+    pub charges_left: usize,
+}
+
+impl Offer {
+    pub fn of(item: &Item) -> Self {
+        Self { id: item.id, count: item.count, charges_left: item.charges_left }
+    }
+}
+
+impl Trade {
+    pub fn new(from: &str, to: &str) -> Self {
+        Self { from: from.to_owned(), to: to.to_owned(), open: false, offers: [vec![], vec![]], confirmed: [false, false] }
+    }
+    pub fn side(&self, name: &str) -> Option<usize> {
+        if self.from == name {
+            Some(0)
+        }
+        else if self.to == name {
+            Some(1)
+        }
+        else {
+            None
+        }
+    }
+    pub fn partner(&self, name: &str) -> &str {
+        if self.from == name {&self.to} else {&self.from}
+    }
+}
+
+impl Game {
+    // a player is in at most one trade or request at a time
+    pub fn trade_of(&self, name: &str) -> Option<&Trade> {
+        self.trades.iter().find(|t| t.side(name).is_some())
+    }
+}
+
+fn close_enough(a: &Player, b: &Player) -> bool {
+    vector::distance((a.get_x(), a.get_y()), (b.get_x(), b.get_y())).2 <= TRADE_DISTANCE
+}
+
+pub fn handle_trades(game: &mut Game) {
+    // cancelled on distance, death, logout and by either side
+    let players = &game.players;
+    game.trades.retain(|t| {
+        let (a, b) = match (players.iter().find(|p| p.name == t.from), players.iter().find(|p| p.name == t.to)) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return false,
+        };
+        a.alive && b.alive && close_enough(a, b) && !a.triggered(InputAction::Cancel) && !b.triggered(InputAction::Cancel)
+    });
+
+    // asking the nearest player, or accepting what somebody asked
+    let requesting: Vec<usize> = (0..game.players.len()).filter(|i| game.players[*i].triggered(InputAction::RequestTrade)).collect();
+    for i in requesting {
+        let name = game.players[i].name.clone();
+        if let Some(trade) = game.trades.iter_mut().find(|t| t.to == name && !t.open) {
+            trade.open = true;
+            let from = trade.from.clone();
+            for player in game.players.iter_mut().filter(|p| p.name == name || p.name == from) {
+                player.inventory.open = true;
+            }
+            continue;
+        }
+        if game.trade_of(&name).is_some() || !game.players[i].alive {continue;}
+        let player = &game.players[i];
+        let partner = game.players.iter()
+            .filter(|p| p.name != name && p.alive && close_enough(player, p) && game.trade_of(&p.name).is_none())
+            .min_by(|a, b| {
+                let da = vector::distance((player.x, player.y), (a.x, a.y)).2;
+                let db = vector::distance((player.x, player.y), (b.x, b.y)).2;
+                da.total_cmp(&db)
+            });
+        if let Some(partner) = partner {
+            let trade = Trade::new(&name, &partner.name);
+            game.trades.push(trade);
+        }
+    }
+
+    let mut done = vec![];
+    for (t, trade) in game.trades.iter_mut().enumerate().filter(|(_, t)| t.open) {
+        for side in 0..2 {
+            let name = if side == 0 {&trade.from} else {&trade.to};
+            let player = game.players.iter().find(|p| p.name == *name).unwrap();
+            // items can be used up or dropped while they are offered, the other side has to see that before it counts
+            let mut changed = false;
+            trade.offers[side].retain_mut(|offer| match player.inventory.index_of(offer.id) {
+                Some(i) => {
+                    let now = Offer::of(&player.inventory.items[i]);
+                    changed |= now != *offer;
+                    *offer = now;
+                    true
+                },
+                None => {
+                    changed = true;
+                    false
+                },
+            });
+            if player.triggered(InputAction::OfferItem) {
+                if let Some(item) = player.inventory.selected_item.map(|s| &player.inventory.items[s]) {
+                    match trade.offers[side].iter().position(|o| o.id == item.id) {
+                        Some(o) => {trade.offers[side].remove(o);},
+                        None => trade.offers[side].push(Offer::of(item)),
+                    }
+                    changed = true;
+                }
+            }
+            if changed {
+                trade.confirmed = [false, false];
+            }
+            if player.triggered(InputAction::ConfirmTrade) {
+                trade.confirmed[side] = true;
+            }
+        }
+        if trade.confirmed == [true, true] {
+            done.push(t);
+        }
+    }
+    for t in done.into_iter().rev() {
+        let trade = game.trades[t].clone();
+        match swap(&mut game.players, &trade) {
+            Ok(()) => {game.trades.remove(t);},
+            // the full message shows who has to make room
+            Err(_) => game.trades[t].confirmed = [false, false],
+        }
+    }
+}
+
+// moves the offered stacks both ways or nothing at all if one side has no room
+fn swap(players: &mut [Player], trade: &Trade) -> Result<(), String> {
+    let a = players.iter().position(|p| p.name == trade.from).ok_or(format!("no player {}", trade.from))?;
+    let b = players.iter().position(|p| p.name == trade.to).ok_or(format!("no player {}", trade.to))?;
+    let (a, b) = if a < b {
+        let (left, right) = players.split_at_mut(b);
+        (&mut left[a], &mut right[0])
+    }
+    else {
+        let (left, right) = players.split_at_mut(a);
+        (&mut right[0], &mut left[b])
+    };
+    let mut full = vec![];
+    for (player, gives, gets) in [(&mut *a, &trade.offers[0], &trade.offers[1]), (&mut *b, &trade.offers[1], &trade.offers[0])] {
+        if player.inventory.items.len() - gives.len() + gets.len() > player.inventory.capacity {
+            player.inventory.full = FULL_TICKS;
+            full.push(player.name.clone());
+        }
+    }
+    if !full.is_empty() {
+        return Err(format!("no room in the inventory of {}", full.join(" and ")));
+    }
+    let to_b = give_away(a, &trade.offers[0]);
+    let to_a = give_away(b, &trade.offers[1]);
+    for (player, items) in [(a, to_a), (b, to_b)] {
+        for mut item in items {
+            item.active = false;
+            // the room was checked, stacks that merge only need less
+            if let Err(item) = player.inventory.add(item) {
+                player.inventory.items.push(item);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn give_away(player: &mut Player, offers: &[Offer]) -> Vec<Item> {
+    let mut items = vec![];
+    for offer in offers {
+        if let Some(i) = player.inventory.index_of(offer.id) {
+            items.push(player.inventory.remove(i));
+        }
+    }
+    items
+}
+
+#[cfg(test)]
+mod trade_tests {
+    use std::sync::mpsc::channel;
+
+    use crate::item::SCROLL;
+
+    use super::*;
+
+    // presses a key for some players for one tick and lets go again
+    fn press(game: &mut Game, keys: &[(usize, &str)]) {
+        for (i, key) in keys {
+            game.players[*i].keys_down = vec![key.to_string()];
+        }
+        for player in game.players.iter_mut() {
+            player.handle_keys(&mut vec![], &mut game.item_counter);
+        }
+        handle_trades(game);
+        for player in game.players.iter_mut() {
+            player.keys_down = vec![];
+            player.handle_keys(&mut vec![], &mut game.item_counter);
+        }
+    }
+
+    fn setup() -> Game {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        let mut a = Player::new(&"a".to_owned());
+        let mut b = Player::new(&"b".to_owned());
+        b.x = 100.0;
+        a.inventory.add(game.create_item("monocle").unwrap()).unwrap();
+        b.inventory.add(game.create_item("heart").unwrap()).unwrap();
+        game.players.push(a);
+        game.players.push(b);
+        game
+    }
+
+    #[test]
+    fn trade_test() {
+        let mut game = setup();
+        press(&mut game, &[(0, "KeyY")]);
+        assert_eq!(game.trades, vec![Trade::new("a", "b")]);
+        press(&mut game, &[(1, "KeyY")]);
+        assert!(game.trades[0].open);
+        assert!(game.players[1].inventory.open);
+
+        press(&mut game, &[(0, "KeyV")]);
+        press(&mut game, &[(0, "Enter")]);
+        // changing the offer takes the confirmation back
+        press(&mut game, &[(1, "KeyV")]);
+        assert_eq!(game.trades[0].confirmed, [false, false]);
+        press(&mut game, &[(0, "Enter"), (1, "Enter")]);
+        assert!(game.trades.is_empty());
+        assert_eq!(game.players[0].inventory.items[0].kind, "heart");
+        assert_eq!(game.players[1].inventory.items[0].kind, "monocle");
+    }
+
+    #[test]
+    fn changed_offer_test() {
+        let mut game = setup();
+        for _ in 0..5 {
+            let scroll = game.create_item(SCROLL).unwrap();
+            game.players[0].inventory.add(scroll).unwrap();
+        }
+        press(&mut game, &[(0, "KeyY")]);
+        press(&mut game, &[(1, "KeyY")]);
+        game.players[0].inventory.selected_item = Some(1);
+        press(&mut game, &[(0, "KeyV")]);
+        press(&mut game, &[(1, "Enter")]);
+        assert_eq!(game.trades[0].confirmed, [false, true]);
+        // dropping part of the offered stack takes the confirmation of the other side back
+        game.players[0].inventory.take_one(1, &mut game.item_counter).unwrap();
+        press(&mut game, &[(0, "Enter")]);
+        assert_eq!(game.trades[0].confirmed, [true, false]);
+        assert_eq!(game.trades[0].offers[0][0].count, 4);
+        press(&mut game, &[(1, "Enter")]);
+        assert!(game.trades.is_empty());
+        assert_eq!(game.players[1].inventory.count(SCROLL), 4);
+    }
+
+    #[test]
+    fn cancel_test() {
+        let mut game = setup();
+        press(&mut game, &[(0, "KeyY")]);
+        press(&mut game, &[(1, "KeyY")]);
+        game.players[1].x = TRADE_DISTANCE * 2.0;
+        handle_trades(&mut game);
+        assert!(game.trades.is_empty());
+
+        game.players[1].x = 100.0;
+        press(&mut game, &[(1, "KeyY")]);
+        game.players[0].alive = false;
+        handle_trades(&mut game);
+        assert!(game.trades.is_empty());
+
+        game.players[0].alive = true;
+        press(&mut game, &[(1, "KeyY")]);
+        game.logout(&"a".to_owned());
+        assert!(game.trades.is_empty());
+    }
+
+    #[test]
+    fn full_test() {
+        let mut game = setup();
+        game.players[1].inventory.capacity = 1;
+        press(&mut game, &[(0, "KeyY")]);
+        press(&mut game, &[(1, "KeyY")]);
+        // a gift to a full inventory waits until there is room
+        press(&mut game, &[(0, "KeyV")]);
+        press(&mut game, &[(0, "Enter"), (1, "Enter")]);
+        assert_eq!(game.trades.len(), 1);
+        assert!(game.players[1].inventory.full > 0);
+        assert_eq!(game.players[0].inventory.items.len(), 1);
+    }
+}
@@ -115,6 +115,19 @@ pub fn get_intersection(a: Line, b: Line) -> Option<(Float, Float)> {
     }
     Some((matrix[0][2], matrix[1][2]))
 }
+// even odd rule, points exactly on an edge can go either way
+pub fn point_in_polygon(point: Point, corners: &[Point]) -> bool {
+    let mut inside = false;
+    let mut j = corners.len().wrapping_sub(1);
+    for i in 0..corners.len() {
+        let (a, b) = (corners[i], corners[j]);
+        if (a.1 > point.1) != (b.1 > point.1) && point.0 < (b.0 - a.0) * (point.1 - a.1) / (b.1 - a.1) + a.0 {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
 
 #[cfg(test)]
 mod tests {
@@ -167,5 +180,13 @@ mod tests {
         let result = get_intersection(a, b);
         assert_eq!(result, None);
     }
-}
 
+    #[test]
+    fn point_in_polygon_test() {
+        let triangle = vec![(0.0, 0.0), (4.0, 0.0), (0.0, 4.0)];
+        assert!(point_in_polygon((1.0, 1.0), &triangle));
+        assert!(!point_in_polygon((3.0, 3.0), &triangle));
+        assert!(!point_in_polygon((-1.0, 1.0), &triangle));
+        assert!(!point_in_polygon((1.0, 1.0), &[]));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{vector, Float};
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum WallType {
     Dirt,
     Wind,
@@ -20,6 +20,13 @@ pub enum WallType {
     Hell,
 }
 
+impl WallType {
+    // spawn areas keep every enemy out, see safezone.rs
+    pub fn is_safe_zone(&self) -> bool {
+        matches!(self, WallType::SpawnA | WallType::SpawnB)
+    }
+}
+
 
 #[derive(Default, Clone)]
 pub struct Wall {
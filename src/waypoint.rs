@@ -0,0 +1,153 @@
+use crate::{game::{DrawPack, Game, Shape}, gametraits::{Position, Radius}, input::InputAction, item::SCROLL, player::Player, revive::HOME, vector, Float};
+
+// waypoints on one page of the menu, one for each digit key
+pub const WAYPOINT_ROWS: usize = 9;
+
+// a checkpoint on the map, found by walking into it
+#[derive(Debug, Clone)]
+pub struct Waypoint {
+    pub name: String,
+    pub x: Float,
+    pub y: Float,
+    pub radius: Float,
+}
+
+impl Game {
+    pub fn spawn_waypoint(&mut self, name: &str, position: (Float, Float), radius: Float) {
+        self.waypoints.push(Waypoint {
+            name: name.to_owned(),
+            x: position.0,
+            y: position.1,
+            radius,
+        });
+        let draw_pack = DrawPack::new("rgba(100,200,255,0.25)", Shape::Circle { radius: Radius::Absolute(radius) }, (0.0, 0.0));
+        self.map.push((position, draw_pack));
+        let draw_pack = DrawPack::new("rgba(255,255,255,0.6)", Shape::Text { content: name.to_owned(), size: 40.0 }, (-radius / 2.0, 0.0));
+        self.map.push((position, draw_pack));
+    }
+    pub fn waypoint(&self, name: &str) -> Option<&Waypoint> {
+        self.waypoints.iter().find(|w| w.name == name)
+    }
+    // where a player respawns, the last checkpoint or home
+    pub fn checkpoint_position(&self, player: &Player) -> (Float, Float) {
+        match player.checkpoint.as_deref().and_then(|name| self.waypoint(name)) {
+            Some(waypoint) => (waypoint.x, waypoint.y),
+            None => HOME,
+        }
+    }
+}
+
+// dead players only get away by paying with scrolls, a scroll revives like it always did
+fn teleport(player: &mut Player, waypoint: &Waypoint, cost: usize) -> bool {
+    if !player.alive && cost == 0 {
+        return false;
+    }
+    if !player.inventory.take(SCROLL, cost) {
+        return false;
+    }
+    player.x = waypoint.x;
+    player.y = waypoint.y;
+    player.old_position = (waypoint.x, waypoint.y);
+    player.alive = true;
+    player.waypoint_menu_open = false;
+    true
+}
+
+pub fn handle_waypoints(game: &mut Game) {
+    let cost = game.teleport_cost;
+    for player in game.players.iter_mut() {
+        let position = (player.get_x(), player.get_y());
+        for waypoint in game.waypoints.iter() {
+            if vector::distance(position, (waypoint.x, waypoint.y)).2 > waypoint.radius {continue;}
+            if !player.waypoints.contains(&waypoint.name) {
+                player.waypoints.push(waypoint.name.clone());
+            }
+            player.checkpoint = Some(waypoint.name.clone());
+        }
+
+        if player.triggered(InputAction::ToggleWaypoints) {
+            player.waypoint_menu_open = !player.waypoint_menu_open;
+        }
+        if !player.waypoint_menu_open {continue;}
+        let pages = player.waypoints.len().div_ceil(WAYPOINT_ROWS).max(1);
+        if player.triggered(InputAction::NextPage) {
+            player.waypoint_page += 1;
+        }
+        if player.triggered(InputAction::PreviousPage) {
+            player.waypoint_page = player.waypoint_page.saturating_sub(1);
+        }
+        player.waypoint_page = player.waypoint_page.min(pages - 1);
+        let first = player.waypoint_page * WAYPOINT_ROWS;
+        let selected = (0..WAYPOINT_ROWS).find(|i| player.triggered(InputAction::Teleport(*i)));
+        let target = selected
+            .and_then(|i| player.waypoints.get(first + i))
+            .and_then(|name| game.waypoints.iter().find(|w| w.name == *name));
+        if let Some(waypoint) = target {
+            teleport(player, waypoint, cost);
+        }
+    }
+}
+
+#[cfg(test)]
+mod waypoint_tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+
+    #[test]
+    fn waypoint_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.teleport_cost = 1;
+        let far = game.waypoints.iter().find(|w| w.x != 0.0 || w.y != 0.0).unwrap().clone();
+        let mut player = Player::new(&"jo".to_owned());
+        (player.x, player.y) = (far.x, far.y);
+        game.players.push(player);
+        handle_waypoints(&mut game);
+        assert_eq!(game.players[0].checkpoint.as_deref(), Some(far.name.as_str()));
+        assert_eq!(game.checkpoint_position(&game.players[0]), (far.x, far.y));
+
+        // back home the menu needs a scroll to get there again
+        (game.players[0].x, game.players[0].y) = (0.0, 0.0);
+        handle_waypoints(&mut game);
+        let index = game.players[0].waypoints.iter().position(|w| *w == far.name).unwrap() + 1;
+        game.players[0].waypoint_menu_open = true;
+        game.players[0].keys_down = vec![format!("Digit{}", index)];
+        game.players[0].handle_keys(&mut vec![], &mut game.item_counter);
+        handle_waypoints(&mut game);
+        assert_eq!(game.players[0].x, 0.0);
+
+        let scroll = game.create_item(SCROLL).unwrap();
+        game.players[0].inventory.items.push(scroll);
+        game.players[0].keys_down = vec![];
+        game.players[0].handle_keys(&mut vec![], &mut game.item_counter);
+        game.players[0].keys_down = vec![format!("Digit{}", index)];
+        game.players[0].handle_keys(&mut vec![], &mut game.item_counter);
+        handle_waypoints(&mut game);
+        assert_eq!((game.players[0].x, game.players[0].y), (far.x, far.y));
+        assert!(game.players[0].inventory.items.is_empty());
+    }
+
+    #[test]
+    fn page_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.teleport_cost = 0;
+        let mut player = Player::new(&"jo".to_owned());
+        for i in 0..12 {
+            let name = format!("w{}", i);
+            game.spawn_waypoint(&name, (100000.0 + 1000.0 * i as Float, 0.0), 100.0);
+            player.waypoints.push(name);
+        }
+        player.waypoint_menu_open = true;
+        game.players.push(player);
+        // past the last page stays on it
+        for key in ["PageDown", "", "PageDown", "", "Digit2"] {
+            game.players[0].keys_down = vec![key.to_owned()];
+            game.players[0].handle_keys(&mut vec![], &mut game.item_counter);
+            handle_waypoints(&mut game);
+        }
+        assert_eq!(game.players[0].waypoint_page, 1);
+        assert_eq!(game.players[0].x, 110000.0);
+    }
+}
@@ -1,9 +1,9 @@
-use std::{io::{Read, Write}, net::TcpStream, sync::mpsc::{self, channel}, thread};
+use std::{io::{self, Read, Write}, net::{Shutdown, TcpStream}, sync::{atomic::{AtomicBool, Ordering}, mpsc::{self, channel}, Arc}, thread};
 
 use base64::prelude::*;
 use sha1::{Sha1, Digest};
 
-use crate::server::{ClientMessage, ServerMessage};
+use crate::{accounts::{self, Credential, Proof}, server::{ClientMessage, Outgoing, ServerMessage}};
 
 pub fn ws_accept_key(key: &str) -> String {
     let magic_string = format!("{}258EAFA5-E914-47DA-95CA-C5AB0DC85B11", key);
@@ -27,9 +27,15 @@ pub fn handle_websocket(sender: mpsc::Sender<ServerMessage>, mut stream: TcpStre
     println!("ws connection established");
 
     let (quit_message_sender, quit_message_receiver) = channel::<()>();
-    let (gms, gmr) = channel::<String>();
+    let (gms, gmr) = channel::<Outgoing>();
 
     let mut send_stream = stream.try_clone().unwrap();
+    // a connection closed by the game was already logged out or never logged in, like a rejected login
+    let closed_by_server = Arc::new(AtomicBool::new(false));
+    let closed = closed_by_server.clone();
+    // the game answers an accepted login with frames and a rejected one only with a close
+    let logged_in = Arc::new(AtomicBool::new(false));
+    let accepted = logged_in.clone();
     let send_handle = thread::spawn(move || {
         loop {
             match quit_message_receiver.try_recv() {
@@ -47,14 +53,34 @@ pub fn handle_websocket(sender: mpsc::Sender<ServerMessage>, mut stream: TcpStre
                 },
             }
             match gmr.try_recv() {
-                Ok(message) => {
-                    send(&mut send_stream, message);
+                Ok(Outgoing::Frame(message)) => {
+                    accepted.store(true, Ordering::SeqCst);
+                    // the client is gone, the read thread notices it too and logs out
+                    if send(&mut send_stream, message).is_err() {
+                        break;
+                    }
+                },
+                Ok(Outgoing::Close(reason)) => {
+                    // the shutdown also ends the read thread
+                    closed.store(true, Ordering::SeqCst);
+                    close(&mut send_stream, 1001, &reason);
+                    let _ = send_stream.shutdown(Shutdown::Both);
+                    break;
+                },
+                Err(std::sync::mpsc::TryRecvError::Empty) => { },
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    // the game dropped this connection without a reason
+                    closed.store(true, Ordering::SeqCst);
+                    close(&mut send_stream, 1000, "");
+                    let _ = send_stream.shutdown(Shutdown::Both);
+                    break;
                 },
-                Err(_) => { },
             }
         }
     });
     let mut username: Option<String> = None;
+    // only the game may hold the sender after the login, so dropping it there closes the connection
+    let mut gms = Some(gms);
     let read_handle = thread::spawn(move || {
         loop {
             match read(&mut stream) {
@@ -62,14 +88,41 @@ pub fn handle_websocket(sender: mpsc::Sender<ServerMessage>, mut stream: TcpStre
                     match serde_json::from_str::<ClientMessage>(&message) {
                         Ok(client_message) => {
                             if client_message.mode == "login".to_owned() {
-                                username = Some(client_message.username.clone());
-                                sender.send(ServerMessage::Login(client_message.username, gms.clone())).unwrap();
+                                match gms.take() {
+                                    Some(gms) => {
+                                        username = Some(client_message.username.clone());
+                                        // argon2 runs here so a flood of logins cant stall the tick
+                                        let proof = match prove_login(&sender, &client_message.username, client_message.credential()) {
+                                            Ok(p) => p,
+                                            Err(reason) => {
+                                                let _ = gms.send(Outgoing::Close(reason));
+                                                continue;
+                                            },
+                                        };
+                                        if sender.send(ServerMessage::Login(client_message.username, proof, gms)).is_err() {
+                                            break;
+                                        }
+                                    },
+                                    None => {
+                                        println!("second login on the same connection was ignored");
+                                    },
+                                }
                             }
-                            else if client_message.mode == "game".to_owned() {
-                                sender.send(ServerMessage::Input { name: client_message.username, mouse: (client_message.x.unwrap(), client_message.y.unwrap()) , keys: client_message.keys_down.unwrap(), wheel: client_message.wheel.unwrap()}).unwrap();
-                            }
-                            else if client_message.mode == "logout".to_owned() {
-                                sender.send(ServerMessage::Logout(client_message.username)).unwrap();
+                            else {
+                                // everything after the login is for the player of this connection only
+                                let name = match own_name(&username, logged_in.load(Ordering::SeqCst), &client_message.username) {
+                                    Some(n) => n,
+                                    None => continue,
+                                };
+                                let message = match client_message.mode.as_str() {
+                                    "game" => ServerMessage::Input { name, mouse: (client_message.x.unwrap(), client_message.y.unwrap()) , keys: client_message.keys_down.unwrap(), wheel: client_message.wheel.unwrap(), movement: client_message.movement},
+                                    "bind" => ServerMessage::Bind { name, key: client_message.key, action: client_message.action },
+                                    "logout" => ServerMessage::Logout(name),
+                                    _ => continue,
+                                };
+                                if sender.send(message).is_err() {
+                                    break;
+                                }
                             }
                         },
                         Err(_) => {
@@ -78,12 +131,12 @@ pub fn handle_websocket(sender: mpsc::Sender<ServerMessage>, mut stream: TcpStre
                     };
                 },
                 Err(_) => {
-                    quit_message_sender.send(()).unwrap();
+                    let _ = quit_message_sender.send(());
                     match username {
-                        Some(username) => {
-                            sender.send(ServerMessage::Logout(username)).unwrap();
+                        Some(username) if logged_in.load(Ordering::SeqCst) && !closed_by_server.load(Ordering::SeqCst) => {
+                            let _ = sender.send(ServerMessage::Logout(username));
                         },
-                        None => { },
+                        _ => { },
                     }
                     break;
                 },
@@ -95,9 +148,30 @@ pub fn handle_websocket(sender: mpsc::Sender<ServerMessage>, mut stream: TcpStre
     read_handle.join().unwrap();
 }
 
+// asks the game for the hash of the account and checks the password against it on this thread
+fn prove_login(sender: &mpsc::Sender<ServerMessage>, name: &str, credential: Option<Credential>) -> Result<Option<Proof>, String> {
+    let password_hash = match credential {
+        Some(Credential::Password(_)) => {
+            let (hash_sender, hash_receiver) = channel();
+            sender.send(ServerMessage::PasswordHash(name.to_owned(), hash_sender)).map_err(|_| "server is shutting down".to_owned())?;
+            hash_receiver.recv().map_err(|_| "server is shutting down".to_owned())?
+        },
+        _ => None,
+    };
+    accounts::prove(password_hash.as_deref(), credential)
+}
+
+// the name the connection logged in with, None before the game accepted the login or for messages naming somebody else
+fn own_name(username: &Option<String>, logged_in: bool, claimed: &str) -> Option<String> {
+    match username {
+        Some(name) if logged_in && name == claimed => Some(name.clone()),
+        _ => None,
+    }
+}
+
 fn read(stream: &mut TcpStream) -> Result<String, ()> {
     let mut framebytes: Vec<u8> = vec![0; 2];
-    stream.read_exact(&mut framebytes).unwrap();
+    stream.read_exact(&mut framebytes).map_err(|_| ())?;
 
     let firstbyte = framebytes.get(0).unwrap();
     let secondbyte = framebytes.get(1).unwrap();
@@ -114,13 +188,13 @@ fn read(stream: &mut TcpStream) -> Result<String, ()> {
 
     if payloadlength == 126 {
         let mut extended_len_bytes = [0u8; 2];
-        stream.read_exact(&mut extended_len_bytes).unwrap();
+        stream.read_exact(&mut extended_len_bytes).map_err(|_| ())?;
         extended_payloadlength = u16::from_be_bytes(extended_len_bytes) as usize;
         payloadlength = 126; // Updating it to flag 126
     }
     else if payloadlength == 127 {
         let mut extended_len_bytes = [0u8; 8];
-        stream.read_exact(&mut extended_len_bytes).unwrap();
+        stream.read_exact(&mut extended_len_bytes).map_err(|_| ())?;
         extended_payloadlength = u64::from_be_bytes(extended_len_bytes) as usize;
     }
 
@@ -132,13 +206,13 @@ fn read(stream: &mut TcpStream) -> Result<String, ()> {
 
     // masking key
     let mut maskingkey = vec![0u8; 4];
-    stream.read_exact(&mut maskingkey).unwrap();
+    stream.read_exact(&mut maskingkey).map_err(|_| ())?;
 
     let mut encoded = vec![0u8; total_payload_len];
-    stream.read_exact(&mut encoded).unwrap();
+    stream.read_exact(&mut encoded).map_err(|_| ())?;
 
     let decoded = crate::websocket::decode(&encoded, &maskingkey);
-    let message = String::from_utf8(decoded).unwrap();
+    let message = String::from_utf8(decoded).map_err(|_| ())?;
     Ok(message)
 }
 
@@ -149,25 +223,25 @@ fn decode(encoded: &Vec<u8>, mask: &Vec<u8>) -> Vec<u8> {
         .collect()
 }
 
-fn send(stream: &mut TcpStream, message: String) {
+fn send(stream: &mut TcpStream, message: String) -> io::Result<()> {
     let message_bytes = message.as_bytes();
     let length = message_bytes.len();
 
     // Text-Frame and FIN flag
-    stream.write_all(&[0x81]).unwrap();
+    stream.write_all(&[0x81])?;
 
     if length <= 125 {
         // Payload length for small messages
-        stream.write_all(&[length as u8]).unwrap();
+        stream.write_all(&[length as u8])?;
     } else if length <= 65535 {
         // Payload length indicator for messages between 126 and 65535
-        stream.write_all(&[126]).unwrap();
+        stream.write_all(&[126])?;
         // Write length in two bytes
-        stream.write_all(&[(length >> 8) as u8, (length & 0xFF) as u8]).unwrap();
+        stream.write_all(&[(length >> 8) as u8, (length & 0xFF) as u8])?;
     }
     else {
         // Payload length indicator for messages larger than 65535
-        stream.write_all(&[127]).unwrap();
+        stream.write_all(&[127])?;
         // Write length in eight bytes (64-bit length)
         // Sending 8 bytes, so pad the first 4 bytes with zeros as length is 64-bit
         stream.write_all(&[
@@ -176,12 +250,53 @@ fn send(stream: &mut TcpStream, message: String) {
             (length >> 16) as u8,
             (length >> 8) as u8,
             (length & 0xFF) as u8,
-        ]).unwrap();
+        ])?;
     }
 
     // Write the message bytes
-    stream.write_all(message_bytes);
+    stream.write_all(message_bytes)?;
     // Ensure all data is sent
-    stream.flush().unwrap();
+    stream.flush()
+}
+
+// at most max bytes without cutting a character in half, the reason of a close frame has to be valid utf-8
+fn truncate(text: &str, max: usize) -> &str {
+    let mut end = max.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+// close frame with a status code and a reason, control frames can only carry 125 bytes
+fn close(stream: &mut TcpStream, code: u16, reason: &str) {
+    let mut payload = code.to_be_bytes().to_vec();
+    payload.extend(truncate(reason, 123).bytes());
+    let mut frame = vec![0x88, payload.len() as u8];
+    frame.append(&mut payload);
+    let _ = stream.write_all(&frame);
+    let _ = stream.flush();
 }
 
+#[cfg(test)]
+mod websocket_tests {
+    use super::*;
+
+    #[test]
+    fn own_name_test() {
+        let jo = Some("jo".to_owned());
+        assert_eq!(own_name(&jo, true, "jo"), Some("jo".to_owned()));
+        assert_eq!(own_name(&jo, true, "admin"), None);
+        // a login the game didnt accept yet, or never will
+        assert_eq!(own_name(&jo, false, "jo"), None);
+        assert_eq!(own_name(&None, true, "jo"), None);
+    }
+
+    #[test]
+    fn truncate_test() {
+        assert_eq!(truncate("kicked", 123), "kicked");
+        // ü takes two bytes
+        assert_eq!(truncate("aü", 2), "a");
+        assert_eq!(truncate("aü", 3), "aü");
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{action::Action, enemy::{self, EnemyEffect}, game::{DrawPack, Game, Shape}, gametraits::{Position, Radius, RadiusTrait}, vector, Float};
+
+#[derive(Debug, Clone)]
+pub enum WorldObjectKind {
+    // pulls enemies towards the middle, placed by the blackhole
+    GravityWell {power: Float},
+    // slows enemies passing through for duration ticks, placed by the puddle
+    SlowZone {power: Float, duration: usize},
+}
+
+// placed into the world by items, stays there until its lifetime runs out
+#[derive(Debug, Clone)]
+pub struct WorldObject {
+    pub x: Float,
+    pub y: Float,
+    pub radius: Float,
+    pub ticks_left: usize,
+    // name of the player that placed it
+    pub owner: String,
+    // id of the item that placed it
+    pub origin: usize,
+    pub kind: WorldObjectKind,
+}
+
+impl WorldObject {
+    pub fn draw_packs(&self) -> Vec<DrawPack> {
+        let radius = Radius::Absolute(self.radius);
+        match self.kind {
+            WorldObjectKind::GravityWell { .. } => vec![
+                DrawPack::new("rgba(80,0,120,0.25)", Shape::Circle { radius }, (0.0, 0.0)),
+                DrawPack::new("rgb(10,0,20)", Shape::Circle { radius: Radius::Absolute(25.0) }, (0.0, 0.0)),
+            ],
+            WorldObjectKind::SlowZone { .. } => vec![
+                DrawPack::new("rgba(40,90,200,0.3)", Shape::Circle { radius }, (0.0, 0.0)),
+                DrawPack::new("rgba(255,255,255,0.5)", Shape::Text { content: self.owner.clone(), size: 16.0 }, (-self.radius / 2.0, 5.0)),
+            ],
+        }
+    }
+}
+
+pub fn handle_world_objects(game: &mut Game) {
+    let mut actions: Vec<(usize, Action)> = vec![];
+    for object in game.world_objects.iter() {
+        for (g, group) in game.enemies.iter_mut().enumerate() {
+            for (i, enemy) in group.1.iter_mut().enumerate() {
+                if enemy.sleeping {continue;}
+                let reach = object.radius + enemy.get_radius();
+                match object.kind {
+                    WorldObjectKind::GravityWell { power } => {
+                        // EnemyEffect::Push the other way round
+                        if let Some((x, y)) = enemy::push((object.x, object.y), (enemy.get_x(), enemy.get_y()), reach, -power) {
+                            actions.push((i, Action::AddEnemyPosition { group: g, x, y }));
+                        }
+                    },
+                    WorldObjectKind::SlowZone { power, duration } => {
+                        if vector::distance((object.x, object.y), (enemy.get_x(), enemy.get_y())).2 > reach {continue;}
+                        // refresh the slow of this puddle instead of stacking it
+                        let effect = enemy.effects.iter_mut().find(|e| matches!(e, EnemyEffect::SpeedAlter { origin, .. } if *origin == object.origin));
+                        match effect {
+                            Some(EnemyEffect::SpeedAlter { ease, .. }) => *ease = duration,
+                            _ => enemy.effects.push(EnemyEffect::SpeedAlter { origin: object.origin, power, ease: duration }),
+                        }
+                    },
+                }
+            }
+        }
+    }
+    for (entity, action) in actions.iter().rev() {
+        action.execute(game, *entity);
+    }
+    for object in game.world_objects.iter_mut() {
+        object.ticks_left = object.ticks_left.saturating_sub(1);
+    }
+    game.world_objects.retain(|o| o.ticks_left > 0);
+}
+
+#[cfg(test)]
+mod worldobject_tests {
+    use std::sync::mpsc::channel;
+
+    use crate::{enemy::Enemy, item, player::Player};
+
+    use super::*;
+
+    fn object(kind: WorldObjectKind, ticks_left: usize) -> WorldObject {
+        WorldObject { x: 0.0, y: 0.0, radius: 500.0, ticks_left, owner: "jo".to_owned(), origin: 7, kind }
+    }
+
+    #[test]
+    fn gravity_well_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.enemies.clear();
+        game.enemies.push((vec![], vec![Enemy::new(300.0, 0.0, (0.0, 0.0), 10.0, "black"), Enemy::new(3000.0, 0.0, (0.0, 0.0), 10.0, "black"), Enemy::new(5.0, 0.0, (0.0, 0.0), 10.0, "black")]));
+        game.world_objects.push(object(WorldObjectKind::GravityWell { power: 10.0 }, 2));
+        handle_world_objects(&mut game);
+        assert_eq!(game.enemies[0].1[0].x, 290.0);
+        assert_eq!(game.enemies[0].1[1].x, 3000.0);
+        // enemies close to the middle are not flung past it
+        assert_eq!(game.enemies[0].1[2].x, 0.0);
+        handle_world_objects(&mut game);
+        assert!(game.world_objects.is_empty());
+    }
+
+    #[test]
+    fn slow_zone_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        game.enemies.clear();
+        game.enemies.push((vec![], vec![Enemy::new(300.0, 0.0, (0.0, 0.0), 10.0, "black")]));
+        game.world_objects.push(object(WorldObjectKind::SlowZone { power: 0.5, duration: 20 }, 100));
+        for _ in 0..3 {
+            handle_world_objects(&mut game);
+        }
+        // passing through again refreshes the slow without stacking it
+        assert_eq!(game.enemies[0].1[0].effects.len(), 1);
+        assert!(matches!(game.enemies[0].1[0].effects[0], EnemyEffect::SpeedAlter { ease: 20, origin: 7, .. }));
+    }
+
+    #[test]
+    fn placing_test() {
+        let (_sender, receiver) = channel();
+        let mut game = Game::new(receiver);
+        let mut player = Player::new(&"jo".to_owned());
+        (player.x, player.mouse) = (100.0, (200.0, 0.0));
+        player.inventory.items.push(game.create_item("blackhole").unwrap());
+        player.inventory.items.push(game.create_item("puddle").unwrap());
+        game.players.push(player);
+        for _ in 0..2 {
+            for item in game.players[0].inventory.items.iter_mut() {
+                item.use_item();
+            }
+            item::handle_effects(&mut game);
+        }
+        // the second use is still cooling down
+        assert_eq!(game.world_objects.len(), 2);
+        let well = game.world_objects.iter().find(|o| matches!(o.kind, WorldObjectKind::GravityWell { .. })).unwrap();
+        assert_eq!(well.x, 300.0);
+        // puddles are dropped where the player stands
+        let puddle = game.world_objects.iter().find(|o| matches!(o.kind, WorldObjectKind::SlowZone { .. })).unwrap();
+        assert_eq!((puddle.x, puddle.owner.as_str()), (100.0, "jo"));
+        assert!(!game.players[0].inventory.items[0].active);
+    }
+}